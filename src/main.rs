@@ -1,44 +1,345 @@
 use std::env;
+use std::io::{self, BufRead, Read, Write};
+use std::path::{Path, PathBuf};
+use std::process;
 
+use pushr::push::configuration::PushConfiguration;
+use pushr::push::debugger::Debugger;
 use pushr::push::instructions::InstructionSet;
-use pushr::push::interpreter::PushInterpreter;
-use pushr::push::parser::PushParser;
-use pushr::push::state::PushState;
+use pushr::push::interpreter::{PushInterpreter, PushObserver, RunReport};
 use pushr::push::item::Item;
+use pushr::push::parser::{ParseError, PushParser};
+use pushr::push::program::PushProgram;
+use pushr::push::state::PushState;
 
-fn main() {
-    println!("> ------------------");
-    println!(">      PUSHR        ");
-    println!("> ------------------");
+const USAGE: &str = "Usage: pushr [OPTIONS] [FILE]
 
-    let args: Vec<String> = env::args().collect(); 
-    if args.len() < 2 {
-        println!("No input ... Done");
-        return;
-    }
-    let input = &args[1]; 
-    println!("Input = {}", input);
+Runs the Push program in FILE or read from stdin if FILE is '-' or missing.
+If FILE does not exist, it is run as inline code, e.g. pushr \"( 1 2 INTEGER.+ )\".
+With --repl, FILE is optional and loaded into the debugger without running it.
 
-    let mut push_state = PushState::new();
-    let mut instruction_set = InstructionSet::new();
-    let instruction_cache = instruction_set.cache();
+Options:
+  --quiet              Print nothing
+  --trace              Print the executed item and the EXEC, CODE and INT stacks after every step
+  --json               Print the final state and the run report as JSON (requires feature serde)
+  --repl               Start an interactive session to execute code and debug the program
+  --seed N             Seed of the random number generator
+  --bool B             Push B onto the BOOLEAN stack
+  --int I              Push I onto the INTEGER stack
+  --float F            Push F onto the FLOAT stack
+  --code CODE          Push the parsed CODE onto the CODE stack
+  --FIELD VALUE        Set a field of the configuration, e.g. --eval-push-limit 200
+  --help               Print this message
 
-    // Load program
-    instruction_set.load();
-    PushParser::parse_program(&mut push_state, &instruction_set, &input);
-    PushParser::copy_to_code_stack(&mut push_state);
+Stack values are pushed in the given order, i.e. the last one ends up on top.";
+
+#[derive(Debug, PartialEq)]
+enum Output {
+    Quiet,
+    Trace,
+    FinalState,
+    Json,
+}
 
-    // Inject interpreter binary 
-    push_state.name_bindings.insert("BIN".to_string(), Item::id(args[0].clone())); 
+/// Command line options.
+struct Options {
+    // Program file or inline code, None for stdin
+    program: Option<PathBuf>,
+    output: Output,
+    seed: Option<u64>,
+    configuration: PushConfiguration,
+    // Initial stack values as (stack option, value) in order of appearance
+    stacks: Vec<(String, String)>,
+    // Start the REPL instead of running the program
+    repl: bool,
+    help: bool,
+}
 
-    loop {
+impl Options {
+    fn parse(args: &[String]) -> Result<Options, String> {
+        let mut options = Options {
+            program: None,
+            output: Output::FinalState,
+            seed: None,
+            configuration: PushConfiguration::new(),
+            stacks: vec![],
+            repl: false,
+            help: false,
+        };
+        let mut args = args.iter();
+        while let Some(arg) = args.next() {
+            let mut value = || {
+                args.next()
+                    .ok_or_else(|| format!("missing value for {}", arg))
+            };
+            match arg.as_str() {
+                "--quiet" => options.output = Output::Quiet,
+                "--trace" => options.output = Output::Trace,
+                "--json" if cfg!(feature = "serde") => options.output = Output::Json,
+                "--json" => return Err("--json requires the feature serde".to_string()),
+                "--repl" => options.repl = true,
+                "--help" => options.help = true,
+                "--seed" => {
+                    let seed = value()?;
+                    options.seed = Some(
+                        seed.parse()
+                            .map_err(|_| format!("invalid value '{}' for --seed", seed))?,
+                    );
+                }
+                "--bool" | "--int" | "--float" | "--code" => {
+                    let stack_value = value()?.clone();
+                    options.stacks.push((arg[2..].to_string(), stack_value));
+                }
+                "-" => options.program = None,
+                _ if arg.starts_with("--") => {
+                    let field = arg[2..].replace('-', "_");
+                    let field_value = value()?;
+                    options.configuration.set(&field, field_value)?;
+                }
+                _ if arg.starts_with('-') => return Err(format!("unknown option {}", arg)),
+                _ => options.program = Some(PathBuf::from(arg)),
+            }
+        }
+        Ok(options)
+    }
+}
+
+/// Prints the executed item and the main stacks after every step.
+struct Tracer {}
+
+impl PushObserver for Tracer {
+    fn after_step(&mut self, step: usize, item: &Item, push_state: &PushState) {
+        println!("> STEP  : {} {}", step, item);
         println!("> EXEC  : {}", push_state.exec_stack.to_string());
         println!("> CODE  : {}", push_state.code_stack.to_string());
         println!("> INT   : {}", push_state.int_stack.to_string());
         println!("> ------------ ");
-        if PushInterpreter::step(&mut push_state, &mut instruction_set, &instruction_cache) {
-            break;
-       }
     }
-    println!("Done.");
+}
+
+fn push_value(
+    push_state: &mut PushState,
+    instruction_set: &InstructionSet,
+    stack: &str,
+    value: &str,
+) -> Result<(), String> {
+    let invalid = || format!("invalid value '{}' for --{}", value, stack);
+    match stack {
+        "bool" => match value.to_lowercase().as_str() {
+            "true" => push_state.bool_stack.push(true),
+            "false" => push_state.bool_stack.push(false),
+            _ => return Err(invalid()),
+        },
+        "int" => push_state
+            .int_stack
+            .push(value.parse().map_err(|_| invalid())?),
+        "float" => push_state
+            .float_stack
+            .push(value.parse().map_err(|_| invalid())?),
+        _ => push_state
+            .code_stack
+            .push(PushParser::parse(instruction_set, value).map_err(|error| error.to_string())?),
+    }
+    Ok(())
+}
+
+#[cfg(feature = "serde")]
+fn print_json(push_state: &PushState, report: &RunReport) -> Result<(), String> {
+    let json = serde_json::json!({
+        "report": report,
+        "push_state": push_state,
+    });
+    println!(
+        "{}",
+        serde_json::to_string_pretty(&json).map_err(|e| e.to_string())?
+    );
+    Ok(())
+}
+
+#[cfg(not(feature = "serde"))]
+fn print_json(_push_state: &PushState, _report: &RunReport) -> Result<(), String> {
+    Err("JSON output requires the feature serde".to_string())
+}
+
+/// Loads the program file or parses the argument as inline code if there is
+/// no such file.
+fn load_program(instruction_set: &InstructionSet, path: &Path) -> Result<PushProgram, ParseError> {
+    if path.exists() {
+        PushProgram::load(instruction_set, path)
+    } else {
+        PushProgram::parse(instruction_set, &path.to_string_lossy(), Path::new("."))
+    }
+}
+
+/// Loads the program and the initial stack values into the state. The
+/// program is copied onto the CODE stack when it starts running.
+fn load_state(
+    push_state: &mut PushState,
+    instruction_set: &InstructionSet,
+    program: Option<PushProgram>,
+    stacks: &[(String, String)],
+) -> Result<(), String> {
+    if let Some(program) = program {
+        program.load_into(push_state);
+    }
+    for (stack, value) in stacks.iter() {
+        push_value(push_state, instruction_set, stack, value)?;
+    }
+    Ok(())
+}
+
+/// Reads commands and code line by line until the end of the input or :quit.
+fn repl(mut debugger: Debugger, instruction_set: &InstructionSet) -> Result<(), String> {
+    println!("Enter :help for a list of commands and :quit to exit.");
+    let stdin = io::stdin();
+    let mut lines = stdin.lock().lines();
+    loop {
+        print!("pushr> ");
+        io::stdout().flush().map_err(|error| error.to_string())?;
+        let line = match lines.next() {
+            Some(line) => line.map_err(|error| error.to_string())?,
+            None => return Ok(()),
+        };
+        if line.trim() == ":quit" {
+            return Ok(());
+        }
+        let output = debugger.command(instruction_set, &line);
+        if !output.is_empty() {
+            println!("{}", output);
+        }
+    }
+}
+
+fn run(args: &[String]) -> Result<(), String> {
+    let options = Options::parse(&args[1..])?;
+    if options.help {
+        println!("{}", USAGE);
+        return Ok(());
+    }
+
+    // Define State and Instruction Set
+    let mut push_state = match options.seed {
+        Some(seed) => PushState::with_seed(seed),
+        None => PushState::new(),
+    };
+    push_state.configuration = options.configuration;
+    let mut instruction_set = InstructionSet::new();
+    instruction_set.load();
+
+    // Load program
+    let program = match &options.program {
+        Some(path) => Some(load_program(&instruction_set, path)),
+        None if options.repl => None,
+        None => {
+            let mut source = String::new();
+            io::stdin()
+                .read_to_string(&mut source)
+                .map_err(|error| error.to_string())?;
+            Some(PushProgram::parse(
+                &instruction_set,
+                &source,
+                Path::new("."),
+            ))
+        }
+    };
+    let program = program.transpose().map_err(|error| error.to_string())?;
+    load_state(&mut push_state, &instruction_set, program, &options.stacks)?;
+
+    // Inject interpreter binary
+    push_state
+        .name_bindings
+        .insert("BIN".to_string(), Item::id(args[0].clone()));
+
+    if options.repl {
+        return repl(Debugger::new(push_state), &instruction_set);
+    }
+
+    let report = match options.output {
+        Output::Trace => {
+            PushInterpreter::run_with_observer(&mut push_state, &instruction_set, &mut Tracer {})
+        }
+        _ => PushInterpreter::run(&mut push_state, &instruction_set),
+    };
+    match options.output {
+        Output::Quiet => (),
+        Output::Json => print_json(&push_state, &report)?,
+        Output::Trace | Output::FinalState => {
+            println!("> STATE : {:?} after {} steps", report.state, report.steps);
+            print!("{}", push_state);
+        }
+    }
+    Ok(())
+}
+
+fn main() {
+    let args: Vec<String> = env::args().collect();
+    if let Err(error) = run(&args) {
+        eprintln!("error: {}", error);
+        process::exit(1);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn args(args: &str) -> Vec<String> {
+        args.split_whitespace().map(|arg| arg.to_string()).collect()
+    }
+
+    #[test]
+    fn options_parse_flags_and_program() {
+        let options = Options::parse(&args(
+            "--trace --int 4 --eval-push-limit 20 --float 2.0 main.push",
+        ))
+        .unwrap();
+        assert_eq!(options.output, Output::Trace);
+        assert_eq!(options.program, Some(PathBuf::from("main.push")));
+        assert_eq!(options.configuration.eval_push_limit, 20);
+        assert_eq!(
+            options.stacks,
+            vec![
+                ("int".to_string(), "4".to_string()),
+                ("float".to_string(), "2.0".to_string())
+            ]
+        );
+        assert_eq!(Options::parse(&args("-")).unwrap().program, None);
+    }
+
+    #[test]
+    fn options_reject_invalid_flags() {
+        assert!(Options::parse(&args("--int")).is_err());
+        assert!(Options::parse(&args("--unknown-field 1")).is_err());
+        assert!(Options::parse(&args("--growth-cap x")).is_err());
+        assert!(Options::parse(&args("-x")).is_err());
+        assert_eq!(Options::parse(&args("--json")).is_ok(), cfg!(feature = "serde"));
+    }
+
+    #[test]
+    fn missing_file_is_run_as_inline_code() {
+        let mut instruction_set = InstructionSet::new();
+        instruction_set.load();
+        let program = load_program(&instruction_set, Path::new("( 1 2 INTEGER.+ )")).unwrap();
+        assert_eq!(program.code.to_string(), "( 1 2 INTEGER.+ )");
+        let path = env::temp_dir().join(format!("pushr-main-{}.push", process::id()));
+        std::fs::write(&path, "( 3 )").unwrap();
+        let program = load_program(&instruction_set, &path).unwrap();
+        assert_eq!(program.code.to_string(), "( 3 )");
+        let _ = std::fs::remove_file(&path);
+    }
+
+    #[test]
+    fn run_copies_program_once_onto_code_stack() {
+        let mut instruction_set = InstructionSet::new();
+        instruction_set.load();
+        let program =
+            PushProgram::parse(&instruction_set, "( 1 2 INTEGER.+ )", Path::new(".")).unwrap();
+        let mut push_state = PushState::new();
+        let stacks = vec![("code".to_string(), "( 5 )".to_string())];
+        load_state(&mut push_state, &instruction_set, Some(program), &stacks).unwrap();
+        PushInterpreter::run(&mut push_state, &instruction_set);
+        assert_eq!(push_state.int_stack.to_string(), "3");
+        assert_eq!(push_state.code_stack.size(), 2);
+        assert_eq!(push_state.code_stack.to_string(), "( 1 2 INTEGER.+ ) ( 5 )");
+    }
 }
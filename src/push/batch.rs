@@ -0,0 +1,227 @@
+use crate::push::configuration::PushConfiguration;
+use crate::push::instructions::InstructionSet;
+use crate::push::interpreter::{PushInterpreter, RunReport};
+use crate::push::item::Item;
+use crate::push::parser::PushParser;
+use crate::push::state::PushState;
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::thread;
+
+/// Final state and run report of a program for a single case.
+pub struct BatchResult {
+    pub state: PushState,
+    pub report: RunReport,
+}
+
+/// Evaluates many programs on many cases in parallel, e.g. to calculate the
+/// fitness of a population. All worker threads share the instruction set and
+/// run each job on a fresh PushState with a copy of the configuration.
+pub struct BatchRunner {
+    // Number of worker threads
+    pub threads: usize,
+    // Configuration of the state of each job
+    pub configuration: PushConfiguration,
+    // Base seed of the random number generators or None for entropy seeded states.
+    // Each job uses its own stream of the seed, so the results do not depend on the
+    // number of threads.
+    pub seed: Option<u64>,
+}
+
+impl BatchRunner {
+    pub fn new(threads: usize) -> Self {
+        Self {
+            threads: usize::max(threads, 1),
+            configuration: PushConfiguration::new(),
+            seed: None,
+        }
+    }
+
+    /// Creates a runner with one worker thread per available CPU.
+    pub fn with_available_parallelism() -> Self {
+        BatchRunner::new(thread::available_parallelism().map_or(1, |n| n.get()))
+    }
+
+    /// Runs each program for each case. The program is pushed to the EXEC stack
+    /// before setup initializes the stacks for the case, e.g. by pushing input
+    /// values. Results are returned in order, i.e. result[p][c] belongs to the
+    /// program p and the case c.
+    pub fn run_items<S, C>(
+        &self,
+        programs: &[Item],
+        cases: &[C],
+        instruction_set: &InstructionSet,
+        setup: S,
+    ) -> Vec<Vec<BatchResult>>
+    where
+        S: Fn(&mut PushState, &C) + Sync,
+        C: Sync,
+    {
+        self.run_jobs(programs.len(), cases, instruction_set, setup, |push_state, _, p| {
+            push_state.exec_stack.push(programs[p].clone());
+        })
+    }
+
+    /// Same as run_items but parses the program code for each job.
+    pub fn run_programs<S, C>(
+        &self,
+        programs: &[String],
+        cases: &[C],
+        instruction_set: &InstructionSet,
+        setup: S,
+    ) -> Vec<Vec<BatchResult>>
+    where
+        S: Fn(&mut PushState, &C) + Sync,
+        C: Sync,
+    {
+        self.run_jobs(
+            programs.len(),
+            cases,
+            instruction_set,
+            setup,
+            |push_state, instruction_set, p| {
+                PushParser::parse_program(push_state, instruction_set, &programs[p]);
+            },
+        )
+    }
+
+    fn run_jobs<S, C, L>(
+        &self,
+        num_programs: usize,
+        cases: &[C],
+        instruction_set: &InstructionSet,
+        setup: S,
+        load: L,
+    ) -> Vec<Vec<BatchResult>>
+    where
+        S: Fn(&mut PushState, &C) + Sync,
+        C: Sync,
+        L: Fn(&mut PushState, &InstructionSet, usize) + Sync,
+    {
+        let num_jobs = num_programs * cases.len();
+        let next_job = AtomicUsize::new(0);
+        let mut finished_jobs: Vec<(usize, BatchResult)> = Vec::with_capacity(num_jobs);
+        thread::scope(|scope| {
+            let mut workers = vec![];
+            for _ in 0..usize::min(self.threads, num_jobs) {
+                workers.push(scope.spawn(|| {
+                    let mut results = vec![];
+                    loop {
+                        let job = next_job.fetch_add(1, Ordering::Relaxed);
+                        if job >= num_jobs {
+                            break;
+                        }
+                        let mut push_state = self.job_state(job);
+                        load(&mut push_state, instruction_set, job / cases.len());
+                        setup(&mut push_state, &cases[job % cases.len()]);
+                        let report = PushInterpreter::run(&mut push_state, instruction_set);
+                        results.push((
+                            job,
+                            BatchResult {
+                                state: push_state,
+                                report,
+                            },
+                        ));
+                    }
+                    results
+                }));
+            }
+            for worker in workers {
+                finished_jobs.extend(worker.join().unwrap());
+            }
+        });
+        finished_jobs.sort_by_key(|(job, _)| *job);
+        let mut results: Vec<Vec<BatchResult>> = (0..num_programs).map(|_| vec![]).collect();
+        for (job, result) in finished_jobs {
+            results[job / cases.len()].push(result);
+        }
+        results
+    }
+
+    /// Returns the initial state of the job.
+    fn job_state(&self, job: usize) -> PushState {
+        let mut push_state = match self.seed {
+            Some(seed) => {
+                let mut push_state = PushState::with_seed(seed);
+                push_state.rng.set_stream(job as u64);
+                push_state
+            }
+            None => PushState::new(),
+        };
+        push_state.configuration = self.configuration.clone();
+        push_state
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::push::interpreter::PushInterpreterState;
+
+    #[test]
+    fn batch_results_are_returned_in_order() {
+        let mut instruction_set = InstructionSet::new();
+        instruction_set.load();
+        let programs = vec![
+            "( INTEGER.DUP INTEGER.* )".to_string(),
+            "( 1 INTEGER.+ )".to_string(),
+            "( INTEGER.DUP INTEGER.+ )".to_string(),
+        ];
+        let cases: Vec<i32> = (0..10).collect();
+        let results = BatchRunner::new(4).run_programs(
+            &programs,
+            &cases,
+            &instruction_set,
+            |push_state, case| push_state.int_stack.push(*case),
+        );
+        assert_eq!(results.len(), 3);
+        for (c, case) in cases.iter().enumerate() {
+            assert_eq!(results[0][c].report.state, PushInterpreterState::NoErrors);
+            assert_eq!(results[0][c].state.int_stack.copy(0), Some(case * case));
+            assert_eq!(results[1][c].state.int_stack.copy(0), Some(case + 1));
+            assert_eq!(results[2][c].state.int_stack.copy(0), Some(case + case));
+        }
+    }
+
+    #[test]
+    fn batch_jobs_use_configuration_and_seed() {
+        let mut instruction_set = InstructionSet::new();
+        instruction_set.load();
+        let programs = vec!["( INTEGER.RAND INTEGER.RAND )".to_string()];
+        let cases: Vec<i32> = (0..8).collect();
+        let run = |threads: usize| {
+            let mut runner = BatchRunner::new(threads);
+            runner.configuration.min_random_integer = 100;
+            runner.configuration.max_random_integer = 1000000;
+            runner.seed = Some(3);
+            runner.run_programs(&programs, &cases, &instruction_set, |_, _| ())
+        };
+        let first: Vec<String> = run(1)[0].iter().map(|r| r.state.int_stack.to_string()).collect();
+        let second: Vec<String> = run(4)[0].iter().map(|r| r.state.int_stack.to_string()).collect();
+        assert_eq!(first, second);
+        // Each job has its own random numbers
+        assert_ne!(first[0], first[1]);
+        for result in &run(2)[0] {
+            assert!(result.state.int_stack.copy(0).unwrap() >= 100);
+        }
+    }
+
+    #[test]
+    fn batch_runs_parsed_items() {
+        let mut instruction_set = InstructionSet::new();
+        instruction_set.load();
+        let programs = vec![
+            Item::list(vec![Item::instruction("BOOLEAN.NOT".to_string())]),
+            Item::list(vec![]),
+        ];
+        let results = BatchRunner::new(2).run_items(
+            &programs,
+            &[true, false],
+            &instruction_set,
+            |push_state, case| push_state.bool_stack.push(*case),
+        );
+        assert_eq!(results[0][0].state.bool_stack.to_string(), "FALSE");
+        assert_eq!(results[0][1].state.bool_stack.to_string(), "TRUE");
+        assert_eq!(results[1][0].state.bool_stack.to_string(), "TRUE");
+        assert_eq!(results[1][1].state.bool_stack.to_string(), "FALSE");
+    }
+}
@@ -79,7 +79,7 @@ pub fn boolean_or(push_state: &mut PushState, _instruction_cache: &InstructionCa
 pub fn boolean_def(push_state: &mut PushState, _instruction_cache: &InstructionCache) {
     if let Some(name) = push_state.name_stack.pop() {
         if let Some(bval) = push_state.bool_stack.pop() {
-            push_state.name_bindings.insert(
+            push_state.bind(
                 name,
                 Item::Literal {
                     push_type: PushType::Bool { val: bval },
@@ -132,8 +132,7 @@ pub fn boolean_pop(push_state: &mut PushState, _instruction_cache: &InstructionC
 
 /// BOOLEAN.POP: Pops the BOOLEAN stack.
 pub fn boolean_rand(push_state: &mut PushState, _instruction_cache: &InstructionCache) {
-    let mut rng = rand::thread_rng();
-    let bval = rng.gen_range(0..2) == 1;
+    let bval = push_state.rng.gen_range(0..2) == 1;
     push_state.bool_stack.push(bval);
 }
 
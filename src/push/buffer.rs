@@ -1,6 +1,7 @@
 use std::fmt;
 
 #[derive(Debug)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub enum BufferType {
     Queue,
     Stack,
@@ -9,6 +10,7 @@ pub enum BufferType {
 /// https://github.com/stjepangolemac/ringvec
 
 #[derive(Debug)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub struct PushBuffer<T> {
     capacity: usize,
     container: Vec<T>,
@@ -16,6 +18,22 @@ pub struct PushBuffer<T> {
     end: usize,
     len: usize,
     buffer_type: BufferType,
+    // Number of modifications since creation
+    changes: usize,
+    // Previous cells while a delta is recorded, see begin_delta
+    #[cfg_attr(feature = "serde", serde(skip))]
+    delta: Option<BufferDelta<T>>,
+}
+
+/// Previous content of the modified cells and positions of a buffer.
+#[derive(Debug)]
+pub struct BufferDelta<T> {
+    // Index and previous content of each modified cell
+    cells: Vec<(usize, T)>,
+    start: usize,
+    end: usize,
+    len: usize,
+    changes: usize,
 }
 
 impl<T> PushBuffer<T>
@@ -36,6 +54,8 @@ where
             end: 0,
             len: 0,
             buffer_type,
+            changes: 0,
+            delta: None,
         }
     }
 
@@ -46,6 +66,59 @@ where
         self.len
     }
 
+    /// Returns the number of modifications since the creation of the buffer.
+    /// Methods that hand out mutable references count as a modification.
+    pub fn changes(&self) -> usize {
+        self.changes
+    }
+
+    /// Starts recording the previous content of all cells that are modified
+    /// until end_delta is called.
+    pub fn begin_delta(&mut self) {
+        self.delta = Some(BufferDelta {
+            cells: vec![],
+            start: self.start,
+            end: self.end,
+            len: self.len,
+            changes: self.changes,
+        });
+    }
+
+    /// Stops recording and returns the previous content of the modified
+    /// cells or None if the buffer has not been modified.
+    pub fn end_delta(&mut self) -> Option<BufferDelta<T>> {
+        match self.delta.take() {
+            Some(delta) if delta.changes != self.changes => Some(delta),
+            _ => None,
+        }
+    }
+
+    /// Restores the buffer content before the delta has been recorded.
+    pub fn revert(&mut self, delta: BufferDelta<T>) {
+        for (index, cell) in delta.cells {
+            self.container[index] = cell;
+        }
+        self.start = delta.start;
+        self.end = delta.end;
+        self.len = delta.len;
+        self.changes = delta.changes;
+    }
+
+    /// Counts a modification of the cell at index and records its previous
+    /// content if a delta is recorded.
+    fn modify(&mut self, index: usize) {
+        self.changes += 1;
+        self.record(index);
+    }
+
+    fn record(&mut self, index: usize) {
+        if let Some(delta) = &mut self.delta {
+            if !delta.cells.iter().any(|(i, _)| *i == index) {
+                delta.cells.push((index, self.container[index].clone()));
+            }
+        }
+    }
+
     pub fn to_string(&self) -> String {
         let mut result = "".to_string();
         for i in 0..self.size() {
@@ -75,6 +148,10 @@ where
     }
 
     pub fn flush(&mut self)  {
+        for index in 0..self.capacity {
+            self.record(index);
+        }
+        self.changes += 1;
         let capacity = self.capacity;
         self.container = Vec::with_capacity(capacity);
         for _ in 0..capacity {
@@ -121,6 +198,7 @@ where
 
     pub fn get_mut(&mut self, i: usize) -> Option<&mut T> {
         if let Some(index) = self.get_index(i) {
+            self.modify(index);
             return Some(&mut self.container[index])
         }
         None
@@ -152,6 +230,7 @@ where
         if self.is_full() {
             return;
         }
+        self.modify(self.start);
         let cell = &mut self.container[self.start];
         *cell = element;
         self.len += 1;
@@ -160,6 +239,7 @@ where
     }
 
     pub fn push_force(&mut self, element: T) {
+        self.modify(self.start);
         let cell = &mut self.container[self.start];
 
         *cell = element;
@@ -179,6 +259,7 @@ where
             BufferType::Queue => {
                 if let Some(first_index) = self.get_index(0) {
                     //println!("Index {}", first_index);
+                    self.modify(first_index);
                     let cell = self.container.get_mut(first_index).unwrap();
                     let result = std::mem::take(cell);
                     self.len -= 1;
@@ -189,6 +270,7 @@ where
             },
             BufferType::Stack => {
                 if let Some (last_idx) = self.get_index(0) {
+                    self.modify(last_idx);
                     let cell = self.container.get_mut(last_idx).unwrap();
                     let result = std::mem::take(cell);
                     self.len -= 1;
@@ -424,4 +506,26 @@ mod test {
         assert_eq!(i.next(), None);
         assert_eq!(i.next(), None);
     }
+
+    #[test]
+    fn revert_restores_modified_cells() {
+        let mut v = PushBuffer::new(BufferType::Stack, 3);
+        v.push(1);
+        v.push(2);
+        v.begin_delta();
+        v.pop();
+        v.push_force(3);
+        v.push_force(4);
+        v.push_force(5);
+        assert_eq!(v.get(0), Some(&5));
+        let delta = v.end_delta().unwrap();
+        v.revert(delta);
+        assert_eq!((v.get(0), v.get(1), v.size()), (Some(&2), Some(&1), 2));
+        assert_eq!(v.changes(), 2);
+        v.begin_delta();
+        v.flush();
+        let delta = v.end_delta().unwrap();
+        v.revert(delta);
+        assert_eq!(v.pop(), Some(2));
+    }
 }
@@ -1,5 +1,6 @@
 use crate::push::instructions::Instruction;
 use crate::push::instructions::InstructionCache;
+use crate::push::instructions::InstructionId;
 use crate::push::item::Item;
 use crate::push::random::CodeGenerator;
 use crate::push::stack::PushStack;
@@ -7,6 +8,12 @@ use crate::push::state::PushState;
 use crate::push::state::*;
 use std::cmp;
 use std::collections::HashMap;
+use std::sync::{Arc, OnceLock};
+
+// Ids of the instructions that are pushed by CODE instructions
+static CODE_LOOP_ID: OnceLock<InstructionId> = OnceLock::new();
+static CODE_POP_ID: OnceLock<InstructionId> = OnceLock::new();
+static INDEX_INCREASE_ID: OnceLock<InstructionId> = OnceLock::new();
 
 /// For explicit code manipulation and execution. May also be used as a general list data type.
 /// This type must always be present, as the top level interpreter will push any code to be
@@ -73,7 +80,7 @@ pub fn load_code_instructions(map: &mut HashMap<String, Instruction>) {
         Instruction::new(code_position),
     );
     map.insert(String::from("CODE.PRINT"), Instruction::new(code_print));
-    map.insert(String::from("CODE.QUOTE"), Instruction::new(code_quote));
+    map.insert(String::from("CODE.QUOTE"), Instruction::with_blocks(code_quote, 1));
     map.insert(String::from("CODE.RAND"), Instruction::new(code_rand));
     map.insert(String::from("CODE.ROT"), Instruction::new(code_rot));
     map.insert(String::from("CODE.SHOVE"), Instruction::new(code_shove));
@@ -113,7 +120,7 @@ pub fn code_eq(push_state: &mut PushState, _instruction_cache: &InstructionCache
 pub fn code_append(push_state: &mut PushState, _instruction_cache: &InstructionCache) {
     if let Some(pv) = push_state.code_stack.pop_vec(2) {
         push_state.code_stack.push(Item::List {
-            items: PushStack::from_vec(pv),
+            items: Arc::new(PushStack::from_vec(pv)),
         });
     }
 }
@@ -136,7 +143,7 @@ pub fn code_first(push_state: &mut PushState, _instruction_cache: &InstructionCa
     if push_state.code_stack.last_eq(&Item::empty_list()) {
         match push_state.code_stack.pop() {
             Some(Item::List { mut items }) => {
-                if let Some(item) = items.pop() {
+                if let Some(item) = Arc::make_mut(&mut items).pop() {
                     push_state.code_stack.push(item);
                 }
             }
@@ -153,7 +160,7 @@ pub fn code_first(push_state: &mut PushState, _instruction_cache: &InstructionCa
 pub fn code_rest(push_state: &mut PushState, _instruction_cache: &InstructionCache) {
     match push_state.code_stack.pop() {
         Some(Item::List { mut items }) => {
-            items.pop();
+            Arc::make_mut(&mut items).pop();
             push_state.code_stack.push(Item::List { items: items });
         }
         _ => (),
@@ -180,7 +187,9 @@ pub fn code_cons(push_state: &mut PushState, _instruction_cache: &InstructionCac
                 _ => (),
             }
         }
-        push_state.code_stack.push(Item::List { items: consblock });
+        push_state.code_stack.push(Item::List {
+            items: Arc::new(consblock),
+        });
     }
 }
 
@@ -218,7 +227,7 @@ pub fn code_contains(push_state: &mut PushState, _instruction_cache: &Instructio
 pub fn code_define(push_state: &mut PushState, _instruction_cache: &InstructionCache) {
     if let Some(name) = push_state.name_stack.pop() {
         if let Some(instruction) = push_state.code_stack.pop() {
-            push_state.name_bindings.insert(name, instruction);
+            push_state.bind(name, instruction);
         }
     }
 }
@@ -291,7 +300,7 @@ pub fn code_discrepancy(push_state: &mut PushState, _instruction_cache: &Instruc
 pub fn code_do(push_state: &mut PushState, _instruction_cache: &InstructionCache) {
     if let Some(instruction) = push_state.code_stack.copy(0) {
         push_state.exec_stack.push(Item::InstructionMeta {
-            name: "CODE.POP".to_string(),
+            id: InstructionId::cached(&CODE_POP_ID, "CODE.POP"),
         });
         push_state.exec_stack.push(instruction);
     }
@@ -302,7 +311,7 @@ pub fn code_pop_and_do(push_state: &mut PushState, _instruction_cache: &Instruct
     if let Some(instruction) = push_state.code_stack.copy(0) {
         push_state.exec_stack.push(instruction);
         push_state.exec_stack.push(Item::InstructionMeta {
-            name: "CODE.POP".to_string(),
+            id: InstructionId::cached(&CODE_POP_ID, "CODE.POP"),
         });
     }
 }
@@ -324,8 +333,12 @@ pub fn code_loop(push_state: &mut PushState, _instruction_cache: &InstructionCac
             if index.current < index.destination {
                 let updated_loop = Item::list(vec![
                     body.clone(),
-                    Item::instruction("CODE.LOOP".to_string()),
-                    Item::instruction("INDEX.INCREASE".to_string()),
+                    Item::InstructionMeta {
+                        id: InstructionId::cached(&CODE_LOOP_ID, "CODE.LOOP"),
+                    },
+                    Item::InstructionMeta {
+                        id: InstructionId::cached(&INDEX_INCREASE_ID, "INDEX.INCREASE"),
+                    },
                 ]);
                 push_state.exec_stack.push(updated_loop);
                 push_state.exec_stack.push(body);
@@ -557,7 +570,7 @@ pub fn code_rand(push_state: &mut PushState, instruction_cache: &InstructionCach
             i32::abs(push_state.configuration.max_points_in_random_expressions),
         );
         if let Some(rand_item) =
-            CodeGenerator::random_code(&push_state, &instruction_cache, limit as usize)
+            CodeGenerator::random_code(push_state, &instruction_cache, limit as usize)
         {
             push_state.code_stack.push(rand_item);
         }
@@ -1,3 +1,5 @@
+#[derive(Clone)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub struct PushConfiguration {
     // The maximum FLOAT that will be produced as an ephemeral random FLOAT constant or from a call to FLOAT.RAND.
     pub max_random_float: f32,
@@ -44,4 +46,46 @@ impl PushConfiguration {
             max_points_in_program: 100,
         }
     }
+
+    /// Sets the field with the given name to the parsed value,
+    /// e.g. set("eval_push_limit", "200").
+    pub fn set(&mut self, field: &str, value: &str) -> Result<(), String> {
+        fn parse<T: std::str::FromStr>(field: &str, value: &str) -> Result<T, String> {
+            value
+                .parse()
+                .map_err(|_| format!("invalid value '{}' for {}", value, field))
+        }
+        match field {
+            "max_random_float" => self.max_random_float = parse(field, value)?,
+            "min_random_float" => self.min_random_float = parse(field, value)?,
+            "max_random_integer" => self.max_random_integer = parse(field, value)?,
+            "min_random_integer" => self.min_random_integer = parse(field, value)?,
+            "eval_push_limit" => self.eval_push_limit = parse(field, value)?,
+            "eval_time_limit" => self.eval_time_limit = parse(field, value)?,
+            "growth_cap" => self.growth_cap = parse(field, value)?,
+            "new_erc_name_probability" => self.new_erc_name_probability = parse(field, value)?,
+            "max_points_in_random_expressions" => {
+                self.max_points_in_random_expressions = parse(field, value)?
+            }
+            "max_points_in_program" => self.max_points_in_program = parse(field, value)?,
+            _ => return Err(format!("unknown configuration field {}", field)),
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn set_parses_value_of_field() {
+        let mut configuration = PushConfiguration::new();
+        assert_eq!(configuration.set("eval_push_limit", "200"), Ok(()));
+        assert_eq!(configuration.set("min_random_float", "-2.5"), Ok(()));
+        assert_eq!(configuration.eval_push_limit, 200);
+        assert_eq!(configuration.min_random_float, -2.5);
+        assert!(configuration.set("growth_cap", "-1").is_err());
+        assert!(configuration.set("unknown", "1").is_err());
+    }
 }
@@ -0,0 +1,420 @@
+use crate::push::instructions::InstructionSet;
+use crate::push::interpreter::{PushInterpreter, PushInterpreterState, RunBudget};
+use crate::push::item::Item;
+use crate::push::journal::{Journal, StateDelta};
+use crate::push::parser::{ParseError, PushParser};
+use crate::push::state::PushState;
+use std::collections::HashSet;
+use std::time::Duration;
+
+const HELP: &str = "Enter Push code to execute it or one of the commands:
+  :step, :s            Execute the next item of the EXEC stack
+  :next, :n            Execute the next item, a list is executed as a whole
+  :continue, :c        Execute until the next breakpoint
+  :back                Revert the last executed step
+  :break NAME          Stop before the instruction or name NAME is executed
+  :delete NAME         Remove the breakpoint on NAME
+  :breakpoints         List all breakpoints
+  :stack STACK         Print a stack, e.g. :stack INTEGER
+  :state               Print all stacks and name bindings
+  :help                Print this message";
+
+/// Reason why the debugger returned control to the user.
+#[derive(Debug, PartialEq)]
+pub enum DebugStop {
+    // The item on top of the EXEC stack is the instruction or name with a breakpoint
+    Breakpoint(String),
+    // A step has been executed and the EXEC stack is not empty
+    Step,
+    // The EXEC stack is empty or a limit of the configuration has been exceeded
+    Terminated(PushInterpreterState),
+}
+
+/// Number of steps that can be reverted
+const JOURNAL_CAPACITY: usize = 10000;
+
+/// Executes Push code step by step against a persistent state. Breakpoints
+/// are set on instruction names or on names that are bound in the name
+/// bindings and stop the execution before the item is executed. Executed
+/// steps are journaled so that they can be reverted.
+pub struct Debugger {
+    pub push_state: PushState,
+    breakpoints: HashSet<String>,
+}
+
+impl Debugger {
+    /// Creates a debugger for the state. Code that is already on the EXEC
+    /// stack of a state that has not started, e.g. a program file, is treated
+    /// as if it had been loaded.
+    pub fn new(mut push_state: PushState) -> Self {
+        if push_state.journal.is_none() {
+            push_state.journal = Some(Journal::new(JOURNAL_CAPACITY));
+        }
+        let mut debugger = Self {
+            push_state,
+            breakpoints: HashSet::new(),
+        };
+        if !debugger.push_state.started && debugger.push_state.exec_stack.size() > 0 {
+            debugger.start();
+        }
+        debugger
+    }
+
+    /// Pushes the parsed code onto the EXEC stack and the CODE stack. The step
+    /// and time limits of the configuration apply from here. Loading is
+    /// journaled like a step, so it can be reverted with step_back.
+    pub fn load(&mut self, instruction_set: &InstructionSet, code: &str) -> Result<(), ParseError> {
+        let item = PushParser::parse(instruction_set, code)?;
+        let delta = StateDelta::begin(&mut self.push_state);
+        self.push_state.exec_stack.push(item.clone());
+        self.push_state.code_stack.push(item);
+        self.push_state.started = true;
+        self.push_state.steps = 0;
+        self.push_state.elapsed = Duration::from_millis(0);
+        let delta = delta.end(&mut self.push_state);
+        if let Some(journal) = &mut self.push_state.journal {
+            journal.push(delta);
+        }
+        Ok(())
+    }
+
+    /// Copies the EXEC stack of a new state to the CODE stack and resets the
+    /// limits and the journal.
+    fn start(&mut self) {
+        PushInterpreter::copy_to_code_stack(&mut self.push_state);
+        self.push_state.started = true;
+        self.push_state.steps = 0;
+        self.push_state.elapsed = Duration::from_millis(0);
+        if let Some(journal) = &mut self.push_state.journal {
+            journal.clear();
+        }
+    }
+
+    pub fn add_breakpoint(&mut self, name: &str) {
+        self.breakpoints.insert(name.to_string());
+    }
+
+    /// Removes the breakpoint and returns true if it existed.
+    pub fn remove_breakpoint(&mut self, name: &str) -> bool {
+        self.breakpoints.remove(name)
+    }
+
+    /// Returns the breakpoints in alphabetical order.
+    pub fn breakpoints(&self) -> Vec<String> {
+        let mut breakpoints: Vec<String> = self.breakpoints.iter().cloned().collect();
+        breakpoints.sort();
+        breakpoints
+    }
+
+    /// Returns the breakpoint of the item on top of the EXEC stack if any.
+    pub fn breakpoint_at_top(&self) -> Option<String> {
+        let name = match self.push_state.exec_stack.get(0) {
+            Some(Item::InstructionMeta { id }) => id.name().to_string(),
+            Some(Item::Identifier { name }) => name.clone(),
+            _ => return None,
+        };
+        if self.breakpoints.contains(&name) {
+            Some(name)
+        } else {
+            None
+        }
+    }
+
+    /// Executes the item on top of the EXEC stack.
+    pub fn step(&mut self, instruction_set: &InstructionSet) -> DebugStop {
+        let budget = RunBudget::new(1, Duration::MAX);
+        match PushInterpreter::run_for(&mut self.push_state, instruction_set, budget).state {
+            PushInterpreterState::BudgetExhausted => DebugStop::Step,
+            state => DebugStop::Terminated(state),
+        }
+    }
+
+    /// Reverts the last executed step. Returns false if there is no step
+    /// that can be reverted.
+    pub fn step_back(&mut self) -> bool {
+        PushInterpreter::step_back(&mut self.push_state)
+    }
+
+    /// Same as step but executes a list on top of the EXEC stack including all
+    /// of its elements. Stops early at breakpoints inside the list.
+    pub fn step_over(&mut self, instruction_set: &InstructionSet) -> DebugStop {
+        let exec_size = self.push_state.exec_stack.size();
+        let is_list = matches!(self.push_state.exec_stack.get(0), Some(Item::List { .. }));
+        let mut stop = self.step(instruction_set);
+        while is_list && stop == DebugStop::Step && self.push_state.exec_stack.size() >= exec_size {
+            if let Some(name) = self.breakpoint_at_top() {
+                return DebugStop::Breakpoint(name);
+            }
+            stop = self.step(instruction_set);
+        }
+        stop
+    }
+
+    /// Executes until the next breakpoint is reached or the program terminates.
+    /// A breakpoint on top of the EXEC stack is passed.
+    pub fn continue_run(&mut self, instruction_set: &InstructionSet) -> DebugStop {
+        let mut stop = self.step(instruction_set);
+        while stop == DebugStop::Step {
+            if let Some(name) = self.breakpoint_at_top() {
+                return DebugStop::Breakpoint(name);
+            }
+            stop = self.step(instruction_set);
+        }
+        stop
+    }
+
+    /// Returns the content of the stack with the given name, e.g. INTEGER
+    /// or intvector, or None if there is no such stack.
+    pub fn stack(&self, name: &str) -> Option<String> {
+        let push_state = &self.push_state;
+        let stack = match name.to_uppercase().as_str() {
+            "BOOLEAN" => push_state.bool_stack.to_string(),
+            "CODE" => push_state.code_stack.to_string(),
+            "EXEC" => push_state.exec_stack.to_string(),
+            "FLOAT" => push_state.float_stack.to_string(),
+            "INDEX" => push_state.index_stack.to_string(),
+            "INTEGER" => push_state.int_stack.to_string(),
+            "NAME" => push_state.name_stack.to_string(),
+            "BOOLVECTOR" => push_state.bool_vector_stack.to_string(),
+            "FLOATVECTOR" => push_state.float_vector_stack.to_string(),
+            "INTVECTOR" => push_state.int_vector_stack.to_string(),
+            "GRAPH" => push_state.graph_stack.to_string(),
+            "INPUT" => push_state.input_stack.to_string(),
+            "OUTPUT" => push_state.output_stack.to_string(),
+            _ => return None,
+        };
+        Some(stack)
+    }
+
+    /// Executes a line of the REPL and returns the output. Lines starting with
+    /// ':' are commands, all other lines are Push code that is pushed onto the
+    /// EXEC stack and executed until the next breakpoint.
+    pub fn command(&mut self, instruction_set: &InstructionSet, line: &str) -> String {
+        let mut words = line.split_whitespace();
+        let (command, argument) = (words.next().unwrap_or(""), words.next());
+        match (command, argument) {
+            ("", _) => String::new(),
+            (":step", None) | (":s", None) => {
+                let stop = self.step(instruction_set);
+                self.describe(stop)
+            }
+            (":next", None) | (":n", None) => {
+                let stop = self.step_over(instruction_set);
+                self.describe(stop)
+            }
+            (":continue", None) | (":c", None) => {
+                let stop = self.continue_run(instruction_set);
+                self.describe(stop)
+            }
+            (":back", None) => {
+                if self.step_back() {
+                    self.describe(DebugStop::Step)
+                } else {
+                    "no step to revert".to_string()
+                }
+            }
+            (":break", Some(name)) => {
+                self.add_breakpoint(name);
+                format!("breakpoint on {}", name)
+            }
+            (":delete", Some(name)) => {
+                if self.remove_breakpoint(name) {
+                    format!("removed breakpoint on {}", name)
+                } else {
+                    format!("no breakpoint on {}", name)
+                }
+            }
+            (":breakpoints", None) => self.breakpoints().join("\n"),
+            (":stack", Some(name)) => self
+                .stack(name)
+                .unwrap_or_else(|| format!("unknown stack {}", name)),
+            (":state", None) => self.push_state.to_string(),
+            (":help", None) => HELP.to_string(),
+            _ if command.starts_with(':') => format!("invalid command {}, see :help", line.trim()),
+            _ => match self.load(instruction_set, line) {
+                Ok(()) => {
+                    let stop = match self.breakpoint_at_top() {
+                        Some(name) => DebugStop::Breakpoint(name),
+                        None => self.continue_run(instruction_set),
+                    };
+                    self.describe(stop)
+                }
+                Err(error) => error.to_string(),
+            },
+        }
+    }
+
+    fn describe(&self, stop: DebugStop) -> String {
+        match stop {
+            DebugStop::Breakpoint(name) => format!("stopped at {}", name),
+            DebugStop::Step => match self.push_state.exec_stack.get(0) {
+                Some(item) => format!("next: {}", item),
+                None => "EXEC stack is empty".to_string(),
+            },
+            DebugStop::Terminated(state) => {
+                format!("{:?} after {} steps", state, self.push_state.steps)
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn continue_stops_at_instruction_and_name_breakpoints() {
+        let mut instruction_set = InstructionSet::new();
+        instruction_set.load();
+        let mut debugger = Debugger::new(PushState::new());
+        debugger.push_state.name_bindings.insert(
+            "SQUARE".to_string(),
+            PushParser::parse(&instruction_set, "( INTEGER.DUP INTEGER.* )").unwrap(),
+        );
+        debugger.add_breakpoint("SQUARE");
+        debugger.add_breakpoint("INTEGER.*");
+        debugger
+            .load(&instruction_set, "( 3 SQUARE 1 INTEGER.+ )")
+            .unwrap();
+        assert_eq!(
+            debugger.continue_run(&instruction_set),
+            DebugStop::Breakpoint("SQUARE".to_string())
+        );
+        assert_eq!(
+            debugger.continue_run(&instruction_set),
+            DebugStop::Breakpoint("INTEGER.*".to_string())
+        );
+        assert_eq!(debugger.stack("integer"), Some("3 3".to_string()));
+        assert_eq!(
+            debugger.continue_run(&instruction_set),
+            DebugStop::Terminated(PushInterpreterState::NoErrors)
+        );
+        assert_eq!(debugger.push_state.int_stack.to_string(), "10");
+    }
+
+    #[test]
+    fn step_over_executes_list_as_a_whole() {
+        let mut instruction_set = InstructionSet::new();
+        instruction_set.load();
+        let mut debugger = Debugger::new(PushState::new());
+        debugger
+            .load(&instruction_set, "( ( 1 2 INTEGER.+ ) 4 )")
+            .unwrap();
+        assert_eq!(debugger.step(&instruction_set), DebugStop::Step);
+        assert_eq!(debugger.step_over(&instruction_set), DebugStop::Step);
+        assert_eq!(debugger.push_state.int_stack.to_string(), "3");
+        assert_eq!(debugger.push_state.exec_stack.to_string(), "4");
+        assert_eq!(
+            debugger.step_over(&instruction_set),
+            DebugStop::Terminated(PushInterpreterState::NoErrors)
+        );
+    }
+
+    #[test]
+    fn step_back_reverts_executed_steps() {
+        let mut instruction_set = InstructionSet::new();
+        instruction_set.load();
+        let mut debugger = Debugger::new(PushState::new());
+        let empty = debugger.push_state.to_string();
+        debugger
+            .load(&instruction_set, "( 2 3 INTEGER.* )")
+            .unwrap();
+        let loaded = debugger.push_state.to_string();
+        debugger.continue_run(&instruction_set);
+        assert_eq!(debugger.push_state.int_stack.to_string(), "6");
+        assert!(debugger.step_back());
+        assert_eq!(debugger.push_state.int_stack.to_string(), "3 2");
+        assert_eq!(debugger.command(&instruction_set, ":back"), "next: 3");
+        assert_eq!(debugger.command(&instruction_set, ":back"), "next: 2");
+        assert!(debugger.step_back());
+        assert_eq!(debugger.push_state.to_string(), loaded);
+        assert_eq!(debugger.command(&instruction_set, ":back"), "EXEC stack is empty");
+        assert_eq!(debugger.push_state.to_string(), empty);
+        assert_eq!(
+            debugger.command(&instruction_set, ":back"),
+            "no step to revert"
+        );
+    }
+
+    #[test]
+    fn load_keeps_paused_code_and_journal() {
+        let mut instruction_set = InstructionSet::new();
+        instruction_set.load();
+        let mut debugger = Debugger::new(PushState::new());
+        debugger.add_breakpoint("INTEGER.*");
+        assert_eq!(
+            debugger.command(&instruction_set, "( 2 3 INTEGER.* )"),
+            "stopped at INTEGER.*"
+        );
+        let paused = debugger.push_state.to_string();
+        assert_eq!(
+            debugger.command(&instruction_set, "( 4 )"),
+            "stopped at INTEGER.*"
+        );
+        assert_eq!(
+            debugger.stack("CODE"),
+            Some("( 4 ) ( 2 3 INTEGER.* )".to_string())
+        );
+        assert_eq!(debugger.push_state.int_stack.to_string(), "4 3 2");
+        // Revert the step of ( 4 ), the push of 4 and the load
+        for _ in 0..3 {
+            assert!(debugger.step_back());
+        }
+        assert_eq!(debugger.push_state.to_string(), paused);
+        assert!(debugger.step_back());
+    }
+
+    #[test]
+    fn new_loads_code_on_exec_stack() {
+        let mut instruction_set = InstructionSet::new();
+        instruction_set.load();
+        let mut push_state = PushState::new();
+        push_state
+            .exec_stack
+            .push(PushParser::parse(&instruction_set, "( 2 3 INTEGER.* )").unwrap());
+        let mut debugger = Debugger::new(push_state);
+        let loaded = debugger.push_state.to_string();
+        assert_eq!(
+            debugger.command(&instruction_set, ":stack CODE"),
+            "( 2 3 INTEGER.* )"
+        );
+        debugger.continue_run(&instruction_set);
+        assert_eq!(debugger.push_state.code_stack.size(), 1);
+        while debugger.step_back() {}
+        assert_eq!(debugger.push_state.to_string(), loaded);
+    }
+
+    #[test]
+    fn commands_keep_state_between_lines() {
+        let mut instruction_set = InstructionSet::new();
+        instruction_set.load();
+        let mut debugger = Debugger::new(PushState::new());
+        assert_eq!(
+            debugger.command(&instruction_set, "( 2 3 )"),
+            "NoErrors after 3 steps"
+        );
+        assert_eq!(
+            debugger.command(&instruction_set, ":break INTEGER.*"),
+            "breakpoint on INTEGER.*"
+        );
+        assert_eq!(
+            debugger.command(&instruction_set, "INTEGER.*"),
+            "stopped at INTEGER.*"
+        );
+        assert_eq!(
+            debugger.command(&instruction_set, ":s"),
+            "NoErrors after 1 steps"
+        );
+        assert_eq!(debugger.command(&instruction_set, ":stack INTEGER"), "6");
+        assert_eq!(
+            debugger.command(&instruction_set, ":breakpoints"),
+            "INTEGER.*"
+        );
+        assert!(debugger
+            .command(&instruction_set, ":stack X")
+            .starts_with("unknown"));
+        assert!(debugger
+            .command(&instruction_set, ":jump")
+            .starts_with("invalid"));
+    }
+}
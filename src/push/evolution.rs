@@ -0,0 +1,508 @@
+use crate::push::batch::{BatchResult, BatchRunner};
+use crate::push::instructions::{InstructionCache, InstructionSet};
+use crate::push::item::Item;
+use crate::push::random::CodeGenerator;
+use crate::push::selection::{Selector, Tournament};
+use crate::push::state::PushState;
+use crate::push::variation::Variation;
+use rand::Rng;
+use std::cmp::Ordering;
+
+/// Parameters of the evolutionary loop. The operator rates are the
+/// probabilities to create a child by the respective operator. Children
+/// are copies of their parent for the remaining probability.
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct EvolutionConfiguration {
+    // Number of programs in each generation
+    pub population_size: usize,
+    // Evolution stops after this number of generations
+    pub max_generations: usize,
+    // Upper bound (exclusive) for the number of points of an initial program
+    pub max_initial_points: usize,
+    // Number of best individuals that are copied to the next generation
+    pub elitism: usize,
+    pub crossover_rate: f32,
+    pub subtree_mutation_rate: f32,
+    pub point_mutation_rate: f32,
+    pub shrink_rate: f32,
+    // Probability to mutate a literal in a point mutation
+    pub literal_mutation_rate: f32,
+    // Maximum number of points of the code inserted by a subtree mutation
+    pub max_mutation_points: usize,
+    // Evolution stops when the total error of an individual does not exceed this value
+    pub error_threshold: f32,
+}
+
+impl EvolutionConfiguration {
+    pub fn new() -> Self {
+        Self {
+            population_size: 500,
+            max_generations: 100,
+            max_initial_points: 50,
+            elitism: 1,
+            crossover_rate: 0.5,
+            subtree_mutation_rate: 0.2,
+            point_mutation_rate: 0.1,
+            shrink_rate: 0.1,
+            literal_mutation_rate: 0.1,
+            max_mutation_points: 20,
+            error_threshold: 0.0,
+        }
+    }
+}
+
+impl Default for EvolutionConfiguration {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+/// Evaluated program. The errors are stored per case.
+#[derive(Clone)]
+pub struct Individual {
+    pub program: Item,
+    pub errors: Vec<f32>,
+    pub total_error: f32,
+}
+
+impl Individual {
+    /// Sums up the errors of the cases. NaN errors are treated as infinite.
+    pub fn new(program: Item, errors: Vec<f32>) -> Self {
+        let errors: Vec<f32> = errors
+            .into_iter()
+            .map(|e| if e.is_nan() { f32::INFINITY } else { e })
+            .collect();
+        let total_error = errors.iter().sum();
+        Self {
+            program,
+            errors,
+            total_error,
+        }
+    }
+
+    /// Orders individuals by their total error.
+    pub fn compare(&self, other: &Individual) -> Ordering {
+        self.total_error.total_cmp(&other.total_error)
+    }
+}
+
+/// Summary of an evaluated generation.
+#[derive(Debug, Clone, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct GenerationStatistics {
+    pub generation: usize,
+    pub best_total_error: f32,
+    pub mean_total_error: f32,
+    // Number of points of the best program
+    pub best_size: usize,
+    pub mean_size: f32,
+}
+
+impl GenerationStatistics {
+    /// Calculates the statistics of a population that is sorted by total error.
+    pub fn new(generation: usize, population: &[Individual]) -> Self {
+        let n = population.len() as f32;
+        Self {
+            generation,
+            best_total_error: population[0].total_error,
+            mean_total_error: population.iter().map(|i| i.total_error).sum::<f32>() / n,
+            best_size: Item::size(&population[0].program),
+            mean_size: population
+                .iter()
+                .map(|i| Item::size(&i.program) as f32)
+                .sum::<f32>()
+                / n,
+        }
+    }
+}
+
+/// Best individual and the statistics of all evaluated generations.
+pub struct EvolutionResult {
+    pub best: Individual,
+    pub statistics: Vec<GenerationStatistics>,
+    // True if the error threshold has been reached
+    pub solved: bool,
+}
+
+/// Generational PushGP loop. Random code and variation use the random number
+/// generator and configuration of the push state. Programs are evaluated in
+/// parallel by the batch runner with the configuration of the push state and
+/// a seed that is drawn for each generation, so that a run is reproducible
+/// for a seeded state. Parents are selected by tournaments of size 7 unless another
+/// selector is set.
+pub struct Evolution {
+    pub configuration: EvolutionConfiguration,
+    pub push_state: PushState,
+    // Instructions that are used to generate random code
+    pub instructions: InstructionCache,
+    pub runner: BatchRunner,
+    pub selector: Box<dyn Selector>,
+}
+
+impl Evolution {
+    pub fn new(
+        configuration: EvolutionConfiguration,
+        push_state: PushState,
+        instructions: InstructionCache,
+    ) -> Self {
+        Self {
+            configuration,
+            push_state,
+            instructions,
+            runner: BatchRunner::with_available_parallelism(),
+            selector: Box::new(Tournament::new(7)),
+        }
+    }
+
+    /// Evolves programs until the error threshold or the maximum number of
+    /// generations is reached. Each program is run for each case after setup
+    /// has initialized the state for the case. The error function calculates
+    /// the error of the final state for the case, lower is better.
+    pub fn run<C, S, E>(
+        &mut self,
+        instruction_set: &InstructionSet,
+        cases: &[C],
+        setup: S,
+        error: E,
+    ) -> EvolutionResult
+    where
+        S: Fn(&mut PushState, &C) + Sync,
+        C: Sync,
+        E: Fn(&BatchResult, &C) -> f32,
+    {
+        let mut programs = self.initial_programs();
+        let mut statistics = vec![];
+        let mut generation = 0;
+        self.runner.configuration = self.push_state.configuration.clone();
+        loop {
+            self.runner.seed = Some(self.push_state.rng.gen());
+            let population = self.evaluate(programs, instruction_set, cases, &setup, &error);
+            statistics.push(GenerationStatistics::new(generation, &population));
+            let solved = population[0].total_error <= self.configuration.error_threshold;
+            generation += 1;
+            if solved || generation >= self.configuration.max_generations {
+                return EvolutionResult {
+                    best: population[0].clone(),
+                    statistics,
+                    solved,
+                };
+            }
+            programs = self.next_generation(&population);
+        }
+    }
+
+    /// Creates a population of random programs.
+    pub fn initial_programs(&mut self) -> Vec<Item> {
+        // Random code needs at least two as exclusive upper bound
+        let max_points = usize::max(self.configuration.max_initial_points, 2);
+        (0..usize::max(self.configuration.population_size, 1))
+            .map(|_| {
+                CodeGenerator::random_code(&mut self.push_state, &self.instructions, max_points)
+                    .unwrap()
+            })
+            .collect()
+    }
+
+    /// Runs the programs for all cases and returns the individuals sorted by
+    /// their total error.
+    pub fn evaluate<C, S, E>(
+        &self,
+        programs: Vec<Item>,
+        instruction_set: &InstructionSet,
+        cases: &[C],
+        setup: S,
+        error: E,
+    ) -> Vec<Individual>
+    where
+        S: Fn(&mut PushState, &C) + Sync,
+        C: Sync,
+        E: Fn(&BatchResult, &C) -> f32,
+    {
+        let results = self
+            .runner
+            .run_items(&programs, cases, instruction_set, setup);
+        let mut population: Vec<Individual> = programs
+            .into_iter()
+            .zip(results)
+            .map(|(program, program_results)| {
+                let errors = program_results
+                    .iter()
+                    .zip(cases)
+                    .map(|(result, case)| error(result, case))
+                    .collect();
+                Individual::new(program, errors)
+            })
+            .collect();
+        // Stable sort keeps the order of equally fit individuals
+        population.sort_by(|a, b| a.compare(b));
+        population
+    }
+
+    /// Creates the programs of the next generation from a population that is
+    /// sorted by total error. The best individuals are copied unchanged.
+    pub fn next_generation(&mut self, population: &[Individual]) -> Vec<Item> {
+        let size = usize::max(self.configuration.population_size, 1);
+        let mut programs: Vec<Item> = population
+            .iter()
+            .take(usize::min(self.configuration.elitism, size))
+            .map(|i| i.program.clone())
+            .collect();
+        self.selector.prepare(&mut self.push_state, population);
+        while programs.len() < size {
+            let child = self.breed(population);
+            programs.push(child);
+        }
+        programs
+    }
+
+    /// Creates a child from parents that are selected from the population.
+    fn breed(&mut self, population: &[Individual]) -> Item {
+        let config = &self.configuration;
+        let operator = self.push_state.rng.gen::<f32>();
+        let crossover = config.crossover_rate;
+        let subtree = crossover + config.subtree_mutation_rate;
+        let point = subtree + config.point_mutation_rate;
+        let shrink = point + config.shrink_rate;
+        let literal_mutation_rate = config.literal_mutation_rate;
+        let max_mutation_points = config.max_mutation_points;
+        let parent = self
+            .selector
+            .select(&mut self.push_state, population)
+            .program
+            .clone();
+        if operator < crossover {
+            let other = self
+                .selector
+                .select(&mut self.push_state, population)
+                .program
+                .clone();
+            Variation::crossover(&mut self.push_state, &parent, &other)
+        } else if operator < subtree {
+            Variation::subtree_mutation(
+                &mut self.push_state,
+                &self.instructions,
+                &parent,
+                max_mutation_points,
+            )
+        } else if operator < point {
+            Variation::point_mutation(&mut self.push_state, &parent, literal_mutation_rate)
+        } else if operator < shrink {
+            Variation::shrink_mutation(&mut self.push_state, &parent)
+        } else {
+            parent
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::push::index::Index;
+    use crate::push::instructions::InstructionId;
+    use crate::push::parser::PushParser;
+    use crate::push::selection::Lexicase;
+
+    fn integer_instructions() -> InstructionCache {
+        InstructionCache::new(
+            vec![
+                "INTEGER.+",
+                "INTEGER.-",
+                "INTEGER.*",
+                "INTEGER.DUP",
+                "INTEGER.SWAP",
+            ]
+            .into_iter()
+            .map(String::from)
+            .collect(),
+        )
+    }
+
+    fn setup(push_state: &mut PushState, case: &(i32, i32)) {
+        push_state.int_stack.push(case.0);
+    }
+
+    fn error(result: &BatchResult, case: &(i32, i32)) -> f32 {
+        match result.state.int_stack.copy(0) {
+            Some(val) => (val as f32 - case.1 as f32).abs(),
+            None => 1000.0,
+        }
+    }
+
+    #[test]
+    fn individuals_are_ordered_by_total_error() {
+        let first = Individual::new(Item::int(1), vec![1.0, 2.0]);
+        let second = Individual::new(Item::int(2), vec![f32::NAN, 0.0]);
+        assert_eq!(first.total_error, 3.0);
+        assert_eq!(second.total_error, f32::INFINITY);
+        assert_eq!(first.compare(&second), Ordering::Less);
+    }
+
+    #[test]
+    fn evaluate_sorts_population() {
+        let mut instruction_set = InstructionSet::new();
+        instruction_set.load();
+        let evolution = Evolution::new(
+            EvolutionConfiguration::new(),
+            PushState::with_seed(1),
+            integer_instructions(),
+        );
+        let programs = vec![
+            PushParser::parse(&instruction_set, "( INTEGER.DUP INTEGER.+ )").unwrap(),
+            PushParser::parse(&instruction_set, "( INTEGER.DUP INTEGER.* )").unwrap(),
+        ];
+        let cases = vec![(1, 1), (2, 4), (3, 9)];
+        let population = evolution.evaluate(programs, &instruction_set, &cases, setup, error);
+        assert_eq!(
+            population[0].program.to_string(),
+            "( INTEGER.DUP INTEGER.* )"
+        );
+        assert_eq!(population[0].total_error, 0.0);
+        assert_eq!(population[1].errors, vec![1.0, 0.0, 3.0]);
+    }
+
+    #[test]
+    fn evolution_solves_integer_regression() {
+        let mut instruction_set = InstructionSet::new();
+        instruction_set.load();
+        let mut configuration = EvolutionConfiguration::new();
+        configuration.population_size = 200;
+        configuration.max_generations = 50;
+        configuration.max_initial_points = 10;
+        let mut evolution = Evolution::new(
+            configuration,
+            PushState::with_seed(2),
+            integer_instructions(),
+        );
+        let cases: Vec<(i32, i32)> = (-5..6).map(|x| (x, x * x + x)).collect();
+        let result = evolution.run(&instruction_set, &cases, setup, error);
+        assert!(result.solved);
+        assert_eq!(result.best.total_error, 0.0);
+        assert_eq!(
+            result.statistics.len(),
+            result.statistics.last().unwrap().generation + 1
+        );
+        // The elite keeps the best error from increasing
+        for window in result.statistics.windows(2) {
+            assert!(window[1].best_total_error <= window[0].best_total_error);
+        }
+    }
+
+    #[test]
+    fn evolution_with_lexicase_selection() {
+        let mut instruction_set = InstructionSet::new();
+        instruction_set.load();
+        let mut configuration = EvolutionConfiguration::new();
+        configuration.population_size = 200;
+        configuration.max_generations = 50;
+        configuration.max_initial_points = 10;
+        let mut evolution =
+            Evolution::new(configuration, PushState::with_seed(5), integer_instructions());
+        evolution.selector = Box::new(Lexicase::new());
+        let cases: Vec<(i32, i32)> = (-5..6).map(|x| (x, x * x - x)).collect();
+        let result = evolution.run(&instruction_set, &cases, setup, error);
+        assert!(result.solved);
+        assert_eq!(result.best.errors, vec![0.0; cases.len()]);
+    }
+
+    #[test]
+    fn evolution_solves_factorial_with_loop() {
+        let mut instruction_set = InstructionSet::new();
+        instruction_set.load();
+        let instructions = InstructionCache::new(
+            vec![
+                "EXEC.LOOP",
+                "EXEC.DUP",
+                "INDEX.CURRENT",
+                "INTEGER.*",
+                "INTEGER.+",
+                "INTEGER.DUP",
+                "INTEGER.SWAP",
+            ]
+            .into_iter()
+            .map(String::from)
+            .collect(),
+        );
+        let mut configuration = EvolutionConfiguration::new();
+        configuration.population_size = 200;
+        configuration.max_generations = 50;
+        configuration.max_initial_points = 10;
+        let mut evolution =
+            Evolution::new(configuration, PushState::with_seed(2), instructions);
+        evolution.selector = Box::new(Lexicase::new());
+        let cases: Vec<(i32, i32)> = (1..7).map(|n| (n, (1..=n).product())).collect();
+        // The loop index runs from 1 to n and the product starts at 1
+        let setup = |push_state: &mut PushState, case: &(i32, i32)| {
+            let mut index = Index::new(case.0 as usize + 1);
+            index.current = 1;
+            push_state.index_stack.push(index);
+            push_state.int_stack.push(1);
+        };
+        let result = evolution.run(&instruction_set, &cases, setup, error);
+        assert!(result.solved);
+        assert_eq!(result.best.errors, vec![0.0; cases.len()]);
+        let exec_loop = Item::instruction("EXEC.LOOP".to_string());
+        assert!(Item::contains(&result.best.program, &exec_loop, 0).is_ok());
+    }
+
+    #[test]
+    fn evolution_stops_after_max_generations() {
+        let mut instruction_set = InstructionSet::new();
+        instruction_set.load();
+        let mut configuration = EvolutionConfiguration::new();
+        configuration.population_size = 10;
+        configuration.max_generations = 3;
+        configuration.error_threshold = -1.0;
+        let mut evolution = Evolution::new(
+            configuration,
+            PushState::with_seed(3),
+            integer_instructions(),
+        );
+        let cases = vec![(1, 2)];
+        let result = evolution.run(&instruction_set, &cases, setup, error);
+        assert!(!result.solved);
+        assert_eq!(result.statistics.len(), 3);
+        assert_eq!(result.statistics[2].generation, 2);
+        assert!(result.best.total_error >= 0.0);
+    }
+
+    #[test]
+    fn evolution_is_reproducible_for_seeded_state() {
+        let mut instruction_set = InstructionSet::new();
+        instruction_set.load();
+        let run = |threads: usize| {
+            let mut instructions = integer_instructions();
+            instructions.list.push(InstructionId::intern("INTEGER.RAND"));
+            let mut configuration = EvolutionConfiguration::new();
+            configuration.population_size = 20;
+            configuration.max_generations = 3;
+            configuration.error_threshold = -1.0;
+            let mut push_state = PushState::with_seed(6);
+            push_state.configuration.eval_push_limit = 50;
+            let mut evolution = Evolution::new(configuration, push_state, instructions);
+            evolution.runner = BatchRunner::new(threads);
+            let result = evolution.run(&instruction_set, &[(1, 2), (2, 3)], setup, error);
+            assert_eq!(evolution.runner.configuration.eval_push_limit, 50);
+            (result.best.program.to_string(), result.best.errors)
+        };
+        assert_eq!(run(1), run(3));
+    }
+
+    #[test]
+    fn next_generation_keeps_elite() {
+        let mut configuration = EvolutionConfiguration::new();
+        configuration.population_size = 5;
+        configuration.elitism = 2;
+        let mut evolution = Evolution::new(
+            configuration,
+            PushState::with_seed(4),
+            integer_instructions(),
+        );
+        let population: Vec<Individual> = (0..5)
+            .map(|i| Individual::new(Item::int(i), vec![i as f32]))
+            .collect();
+        let programs = evolution.next_generation(&population);
+        assert_eq!(programs.len(), 5);
+        assert_eq!(programs[0].to_string(), "0");
+        assert_eq!(programs[1].to_string(), "1");
+    }
+}
@@ -1,37 +1,44 @@
 use crate::push::instructions::Instruction;
 use crate::push::instructions::InstructionCache;
+use crate::push::instructions::InstructionId;
 use crate::push::item::Item;
 use crate::push::state::PushState;
 use crate::push::state::*;
 use std::collections::HashMap;
 use std::process::Command;
+use std::sync::OnceLock;
 use std::{thread, time::Duration};
 
+// Ids of the instructions that are pushed by EXEC instructions
+static EXEC_LOOP_ID: OnceLock<InstructionId> = OnceLock::new();
+static EXEC_Y_ID: OnceLock<InstructionId> = OnceLock::new();
+static INDEX_INCREASE_ID: OnceLock<InstructionId> = OnceLock::new();
+
 /// Code queued for execution. The EXEC stack maintains the execution state of the Push
 /// interpreter. Instructions that specifically manipulate the EXEC stack can be used to implement
 /// various kinds of control structures. The CODE stack can also be used in this way, but
 /// manipulations to the EXEC stack are "live" in the sense that they are manipulating the actual
 /// execution state of the interpreter, not just code that might later be executed.
 pub fn load_exec_instructions(map: &mut HashMap<String, Instruction>) {
-    map.insert(String::from("EXEC.="), Instruction::new(exec_eq));
+    map.insert(String::from("EXEC.="), Instruction::with_blocks(exec_eq, 2));
     map.insert(String::from("EXEC.CMD"), Instruction::new(exec_cmd));
-    map.insert(String::from("EXEC.DEFINE"), Instruction::new(exec_define));
-    map.insert(String::from("EXEC.LOOP"), Instruction::new(exec_loop));
-    map.insert(String::from("EXEC.DUP"), Instruction::new(exec_dup));
+    map.insert(String::from("EXEC.DEFINE"), Instruction::with_blocks(exec_define, 1));
+    map.insert(String::from("EXEC.LOOP"), Instruction::with_blocks(exec_loop, 1));
+    map.insert(String::from("EXEC.DUP"), Instruction::with_blocks(exec_dup, 1));
     map.insert(String::from("EXEC.FLUSH"), Instruction::new(exec_flush));
     map.insert(String::from("EXEC.ID"), Instruction::new(exec_id));
-    map.insert(String::from("EXEC.IF"), Instruction::new(exec_if));
-    map.insert(String::from("EXEC.K"), Instruction::new(exec_k));
-    map.insert(String::from("EXEC.POP"), Instruction::new(exec_pop));
-    map.insert(String::from("EXEC.ROT"), Instruction::new(exec_rot));
-    map.insert(String::from("EXEC.S"), Instruction::new(exec_s));
-    map.insert(String::from("EXEC.SHOVE"), Instruction::new(exec_shove));
+    map.insert(String::from("EXEC.IF"), Instruction::with_blocks(exec_if, 2));
+    map.insert(String::from("EXEC.K"), Instruction::with_blocks(exec_k, 2));
+    map.insert(String::from("EXEC.POP"), Instruction::with_blocks(exec_pop, 1));
+    map.insert(String::from("EXEC.ROT"), Instruction::with_blocks(exec_rot, 3));
+    map.insert(String::from("EXEC.S"), Instruction::with_blocks(exec_s, 3));
+    map.insert(String::from("EXEC.SHOVE"), Instruction::with_blocks(exec_shove, 1));
     map.insert(
         String::from("EXEC.STACKDEPTH"),
         Instruction::new(exec_stack_depth),
     );
-    map.insert(String::from("EXEC.SWAP"), Instruction::new(exec_swap));
-    map.insert(String::from("EXEC.Y"), Instruction::new(exec_y));
+    map.insert(String::from("EXEC.SWAP"), Instruction::with_blocks(exec_swap, 2));
+    map.insert(String::from("EXEC.Y"), Instruction::with_blocks(exec_y, 1));
     map.insert(String::from("EXEC.YANK"), Instruction::new(exec_yank));
     map.insert(
         String::from("EXEC.YANKDUP"),
@@ -77,7 +84,7 @@ pub fn exec_eq(push_state: &mut PushState, _instruction_cache: &InstructionCache
 pub fn exec_define(push_state: &mut PushState, _instruction_cache: &InstructionCache) {
     if let Some(name) = push_state.name_stack.pop() {
         if let Some(instruction) = push_state.exec_stack.pop() {
-            push_state.name_bindings.insert(name, instruction);
+            push_state.bind(name, instruction);
         }
     }
 }
@@ -99,8 +106,12 @@ pub fn exec_loop(push_state: &mut PushState, _instruction_cache: &InstructionCac
             if index.current < index.destination {
                 let updated_loop = Item::list(vec![
                     body.clone(),
-                    Item::instruction("EXEC.LOOP".to_string()),
-                    Item::instruction("INDEX.INCREASE".to_string()),
+                    Item::InstructionMeta {
+                        id: InstructionId::cached(&EXEC_LOOP_ID, "EXEC.LOOP"),
+                    },
+                    Item::InstructionMeta {
+                        id: InstructionId::cached(&INDEX_INCREASE_ID, "INDEX.INCREASE"),
+                    },
                 ]);
                 push_state.exec_stack.push(updated_loop);
                 push_state.exec_stack.push(body);
@@ -209,7 +220,9 @@ pub fn exec_y(push_state: &mut PushState, _instruction_cache: &InstructionCache)
     if let Some(top_item) = push_state.exec_stack.copy(0) {
         push_state.exec_stack.push(Item::list(vec![
             top_item,
-            Item::instruction("EXEC.Y".to_string()),
+            Item::InstructionMeta {
+                id: InstructionId::cached(&EXEC_Y_ID, "EXEC.Y"),
+            },
         ]));
         push_state.exec_stack.shove(1);
     }
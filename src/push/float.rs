@@ -141,7 +141,7 @@ fn float_cosine(push_state: &mut PushState, _instruction_cache: &InstructionCach
 pub fn float_define(push_state: &mut PushState, _instruction_cache: &InstructionCache) {
     if let Some(name) = push_state.name_stack.pop() {
         if let Some(fval) = push_state.float_stack.pop() {
-            push_state.name_bindings.insert(name, Item::float(fval));
+            push_state.bind(name, Item::float(fval));
         }
     }
 }
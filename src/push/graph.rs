@@ -11,11 +11,26 @@ use std::sync::atomic::{AtomicUsize, Ordering};
 static NODE_COUNTER: AtomicUsize = AtomicUsize::new(1);
 
 #[derive(Clone, Debug, Hash, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub struct Node {
+    #[cfg_attr(feature = "serde", serde(deserialize_with = "deserialize_node_id"))]
     node_id: usize,
     state: i32,
 }
 
+/// Deserializes a node id and makes sure that new nodes do not reuse it.
+#[cfg(feature = "serde")]
+fn deserialize_node_id<'de, D>(deserializer: D) -> Result<usize, D::Error>
+where
+    D: serde::Deserializer<'de>,
+{
+    let node_id: usize = serde::Deserialize::deserialize(deserializer)?;
+    if !Node::reserve_id(node_id) {
+        return Err(serde::de::Error::custom("node id out of range"));
+    }
+    Ok(node_id)
+}
+
 impl Node {
     pub fn new(state: i32) -> Self {
         Self {
@@ -24,6 +39,29 @@ impl Node {
         }
     }
 
+    /// Creates a node with a given id, e.g. from a graph literal.
+    /// New nodes created afterwards do not reuse the id. Returns None
+    /// if the id is too large to be reserved.
+    pub fn with_id(node_id: usize, state: i32) -> Option<Self> {
+        if Node::reserve_id(node_id) {
+            Some(Self { node_id, state })
+        } else {
+            None
+        }
+    }
+
+    /// Makes sure that the counter assigns ids greater than node_id.
+    /// Returns false if there is no greater id.
+    fn reserve_id(node_id: usize) -> bool {
+        match node_id.checked_add(1) {
+            Some(next_id) => {
+                NODE_COUNTER.fetch_max(next_id, Ordering::Relaxed);
+                true
+            }
+            None => false,
+        }
+    }
+
     /// Returns the difference between this node and the
     /// argument node as a String or None if they are
     /// identical.
@@ -86,6 +124,7 @@ impl Node {
 
 
     #[derive(Copy, Clone, Debug)]
+    #[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
     pub struct Edge {
         origin_node_id: usize,
         weight: f32,
@@ -165,6 +204,7 @@ impl Node {
     }
 
     #[derive(Clone, Debug, Default)]
+    #[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
     pub struct Graph {
         // Incoming edge list
         pub edges: HashMap<usize, Vec<Edge>>,
@@ -363,6 +403,19 @@ impl Node {
             node_id.clone()
         }
 
+        /// Adds a new node with the given id and state. An existing
+        /// node with the same id is replaced. Returns false if the id is
+        /// too large to be reserved.
+        pub fn add_node_with_id(&mut self, id: usize, state: i32) -> bool {
+            match Node::with_id(id, state) {
+                Some(node) => {
+                    self.nodes.insert(id, node);
+                    true
+                }
+                None => false,
+            }
+        }
+
         /// Removes the node with the given id and
         /// all its outgoing connections from the graph.
         pub fn remove_node(&mut self, id: usize) {
@@ -1309,5 +1362,25 @@ mod tests {
         assert_eq!(test_state.int_stack.pop().unwrap(),2);
     }
 
+    #[test]
+    fn graph_nodes_with_id_reserve_their_id() {
+        let mut test_graph = Graph::new();
+        let id = test_graph.add_node(0) + 1000;
+        assert!(test_graph.add_node_with_id(id, 1));
+        assert_eq!(test_graph.get_state(&id), Some(1));
+        assert!(test_graph.add_node(2) > id);
+        assert!(Node::with_id(usize::MAX, 0).is_none());
+        assert!(!test_graph.add_node_with_id(usize::MAX, 1));
+        assert_eq!(test_graph.get_state(&usize::MAX), None);
+    }
 
+    #[test]
+    #[cfg(feature = "serde")]
+    fn graph_node_deserialization_rejects_largest_id() {
+        let node: Node = serde_json::from_str("{\"node_id\":7,\"state\":1}").unwrap();
+        assert_eq!(node.get_id(), 7);
+        assert!(Node::new(0).get_id() > 7);
+        let json = format!("{{\"node_id\":{},\"state\":1}}", usize::MAX);
+        assert!(serde_json::from_str::<Node>(&json).is_err());
+    }
 }
@@ -6,6 +6,7 @@ use std::collections::HashMap;
 use std::fmt;
 
 #[derive(Clone, Debug)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub struct Index {
     pub current: usize,
     pub destination: usize,
@@ -1,5 +1,7 @@
 use crate::push::state::PushState;
 use std::collections::HashMap;
+use std::fmt;
+use std::sync::{OnceLock, RwLock};
 
 use crate::push::boolean::*;
 use crate::push::code::*;
@@ -13,81 +15,333 @@ use crate::push::list::*;
 use crate::push::name::*;
 use crate::push::vector::*;
 
+/// Process wide mapping between instruction names and their ids.
+/// Names are never removed so an id stays valid for the lifetime
+/// of the process.
+struct InstructionNames {
+    names: Vec<&'static str>,
+    ids: HashMap<&'static str, u32>,
+}
+
+fn instruction_names() -> &'static RwLock<InstructionNames> {
+    static INSTRUCTION_NAMES: OnceLock<RwLock<InstructionNames>> = OnceLock::new();
+    INSTRUCTION_NAMES.get_or_init(|| {
+        RwLock::new(InstructionNames {
+            names: vec![],
+            ids: HashMap::new(),
+        })
+    })
+}
+
+/// Interned instruction name. Items refer to instructions by id so
+/// that cloning them does not copy strings and the interpreter can
+/// dispatch them without hashing.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash, PartialOrd, Ord)]
+pub struct InstructionId(u32);
+
+impl InstructionId {
+    /// Returns the id of the given name. A new id is assigned
+    /// if the name has not been interned yet.
+    pub fn intern(name: &str) -> InstructionId {
+        if let Some(id) = InstructionId::lookup(name) {
+            return id;
+        }
+        let mut instruction_names = instruction_names().write().unwrap();
+        if let Some(id) = instruction_names.ids.get(name) {
+            return InstructionId(*id);
+        }
+        let name: &'static str = Box::leak(name.to_string().into_boxed_str());
+        let id = instruction_names.names.len() as u32;
+        instruction_names.names.push(name);
+        instruction_names.ids.insert(name, id);
+        InstructionId(id)
+    }
+
+    /// Returns the id of the given name. The name is interned on the first
+    /// call and its id is kept in the cell, e.g. for instructions that push
+    /// other instructions without resolving their names in every call.
+    pub fn cached(cell: &'static OnceLock<InstructionId>, name: &str) -> InstructionId {
+        *cell.get_or_init(|| InstructionId::intern(name))
+    }
+
+    /// Returns the id of the given name or None if the name
+    /// has not been interned yet.
+    pub fn lookup(name: &str) -> Option<InstructionId> {
+        instruction_names()
+            .read()
+            .unwrap()
+            .ids
+            .get(name)
+            .map(|id| InstructionId(*id))
+    }
+
+    /// Returns the name of the instruction.
+    pub fn name(&self) -> &'static str {
+        instruction_names().read().unwrap().names[self.0 as usize]
+    }
+
+    /// Returns the position in the dispatch table.
+    pub fn index(&self) -> usize {
+        self.0 as usize
+    }
+}
+
+impl fmt::Display for InstructionId {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", self.name())
+    }
+}
+
+/// Ids are process specific. They are therefore serialized as names and
+/// resolved again on deserialization. Names that are unknown to the process
+/// are rejected instead of interned, so the instruction set has to be
+/// loaded before a state is deserialized.
+#[cfg(feature = "serde")]
+impl serde::Serialize for InstructionId {
+    fn serialize<S: serde::Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.serialize_str(self.name())
+    }
+}
+
+#[cfg(feature = "serde")]
+impl<'de> serde::Deserialize<'de> for InstructionId {
+    fn deserialize<D: serde::Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        let name = String::deserialize(deserializer)?;
+        InstructionId::lookup(&name).ok_or_else(|| {
+            serde::de::Error::custom(format!("unknown instruction {}", name))
+        })
+    }
+}
+
 pub struct InstructionSet {
-    map: HashMap<String, Instruction>,
+    // Dispatch table indexed by InstructionId
+    table: Vec<Option<Instruction>>,
 }
 
 impl InstructionSet {
     pub fn new() -> Self {
-        Self {
-            map: HashMap::new(),
-        }
+        Self { table: vec![] }
     }
 
     /// Load the default instrcution set for the stack types
     /// bool, int, float, code, exec, name and vector types
     pub fn load(&mut self) {
-        self.map
-            .insert(String::from("NOOP"), Instruction::new(noop));
-        load_boolean_instructions(&mut self.map);
-        load_code_instructions(&mut self.map);
-        load_exec_instructions(&mut self.map);
-        load_float_instructions(&mut self.map);
-        load_index_instructions(&mut self.map);
-        load_int_instructions(&mut self.map);
-        load_list_instructions(&mut self.map);
-        load_name_instructions(&mut self.map);
-        load_vector_instructions(&mut self.map);
-        load_io_instructions(&mut self.map);
-        load_graph_instructions(&mut self.map);
-    }
-
-    /// Create a snapshot of the current instruction names
+        let mut map = HashMap::new();
+        map.insert(String::from("NOOP"), Instruction::new(noop));
+        load_boolean_instructions(&mut map);
+        load_code_instructions(&mut map);
+        load_exec_instructions(&mut map);
+        load_float_instructions(&mut map);
+        load_index_instructions(&mut map);
+        load_int_instructions(&mut map);
+        load_list_instructions(&mut map);
+        load_name_instructions(&mut map);
+        load_vector_instructions(&mut map);
+        load_io_instructions(&mut map);
+        load_graph_instructions(&mut map);
+        // Sort names to assign ids independent of the hash map order
+        let mut instructions: Vec<(String, Instruction)> = map.into_iter().collect();
+        instructions.sort_by(|a, b| a.0.cmp(&b.0));
+        for (name, instruction) in instructions {
+            self.add(name, instruction);
+        }
+    }
+
+    /// Create a snapshot of the current instruction ids. The ids are
+    /// sorted by name so that random code only depends on the seed and not
+    /// on the order in which names have been interned.
     pub fn cache(&self) -> InstructionCache {
-        InstructionCache::new(self.map.keys().cloned().collect())
+        let mut ids = self.ids();
+        ids.sort_by(|a, b| a.name().cmp(b.name()));
+        InstructionCache { list: ids }
+    }
+
+    /// Returns the ids of all instructions of this set.
+    pub fn ids(&self) -> Vec<InstructionId> {
+        self.table
+            .iter()
+            .enumerate()
+            .filter(|(_, instruction)| instruction.is_some())
+            .map(|(index, _)| InstructionId(index as u32))
+            .collect()
     }
 
     /// Add a new instruction
     pub fn add(&mut self, name: String, instruction: Instruction) -> Option<Instruction> {
-        self.map.insert(name, instruction)
+        let index = InstructionId::intern(&name).index();
+        if self.table.len() <= index {
+            self.table.resize_with(index + 1, || None);
+        }
+        self.table[index].replace(instruction)
     }
 
     /// Returns true if there exists an instruction
     /// under the given name.
     pub fn is_instruction(&self, name: &str) -> bool {
-        match self.map.get(name) {
-            Some(_i) => true,
+        match InstructionId::lookup(name) {
+            Some(id) => self.contains(id),
+            None => false,
+        }
+    }
+
+    /// Returns true if there exists an instruction
+    /// with the given id.
+    pub fn contains(&self, id: InstructionId) -> bool {
+        match self.table.get(id.index()) {
+            Some(instruction) => instruction.is_some(),
             None => false,
         }
     }
 
-    /// Get a mutable reference of an instruction by name
-    pub fn get_instruction(&mut self, name: &str) -> Option<&mut Instruction> {
-        self.map.get_mut(name)
+    /// Get a reference of an instruction by name
+    pub fn get_instruction(&self, name: &str) -> Option<&Instruction> {
+        match InstructionId::lookup(name) {
+            Some(id) => self.get_instruction_by_id(id),
+            None => None,
+        }
+    }
+
+    /// Returns the number of code blocks the instruction with the
+    /// given id opens or 0 if there is no such instruction.
+    pub fn blocks(&self, id: InstructionId) -> usize {
+        self.get_instruction_by_id(id).map_or(0, |i| i.blocks)
+    }
+
+    /// Get a reference of an instruction by id
+    pub fn get_instruction_by_id(&self, id: InstructionId) -> Option<&Instruction> {
+        match self.table.get(id.index()) {
+            Some(instruction) => instruction.as_ref(),
+            None => None,
+        }
     }
 }
 
+/// Instructions that random code is generated from. The names are interned
+/// once so that generated instructions do not have to be looked up.
 pub struct InstructionCache {
-    pub list: Vec<String>,
+    pub list: Vec<InstructionId>,
 }
 
 impl InstructionCache {
     pub fn new(arg_list: Vec<String>) -> Self {
-        Self { list: arg_list }
+        Self {
+            list: arg_list.iter().map(|name| InstructionId::intern(name)).collect(),
+        }
     }
 }
 
+/// Instructions are immutable so that a loaded instruction set can be shared
+/// between threads, e.g. through an Arc. Instructions that need state have to
+/// keep it in the PushState.
 pub struct Instruction {
-    pub execute: Box<dyn FnMut(&mut PushState, &InstructionCache) + Send>,
+    pub execute: Box<dyn Fn(&mut PushState, &InstructionCache) + Send + Sync>,
+    // Number of code blocks the instruction takes from the EXEC stack. A linear
+    // genome opens this number of blocks after the instruction.
+    pub blocks: usize,
 }
 
 impl Instruction {
-    pub fn new(execute: impl FnMut(&mut PushState, &InstructionCache) + 'static + std::marker::Send) -> Self {
+    pub fn new(execute: impl Fn(&mut PushState, &InstructionCache) + 'static + Send + Sync) -> Self {
+        Instruction::with_blocks(execute, 0)
+    }
+
+    /// Creates an instruction that opens the given number of code blocks.
+    pub fn with_blocks(
+        execute: impl Fn(&mut PushState, &InstructionCache) + 'static + Send + Sync,
+        blocks: usize,
+    ) -> Self {
         Self {
             execute: Box::new(execute),
+            blocks,
         }
     }
 }
 
 /// NOOP: No operation.
 fn noop(_push_state: &mut PushState, _instruction_cache: &InstructionCache) {}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn interned_ids_resolve_to_their_names() {
+        let id = InstructionId::intern("TEST.INTERN");
+        assert_eq!(InstructionId::intern("TEST.INTERN"), id);
+        assert_eq!(InstructionId::lookup("TEST.INTERN"), Some(id));
+        assert_eq!(id.name(), "TEST.INTERN");
+        assert_eq!(id.to_string(), "TEST.INTERN");
+        assert_eq!(InstructionId::lookup("TEST.NOT_INTERNED"), None);
+    }
+
+    #[test]
+    #[cfg(feature = "serde")]
+    fn deserialization_rejects_unknown_names() {
+        let id = InstructionId::intern("TEST.SERDE");
+        let restored: InstructionId = serde_json::from_str("\"TEST.SERDE\"").unwrap();
+        assert_eq!(restored, id);
+        assert!(serde_json::from_str::<InstructionId>("\"TEST.SERDE*UNKNOWN\"").is_err());
+        assert_eq!(InstructionId::lookup("TEST.SERDE*UNKNOWN"), None);
+    }
+
+    #[test]
+    fn cached_ids_are_interned_once() {
+        static ID: OnceLock<InstructionId> = OnceLock::new();
+        let id = InstructionId::cached(&ID, "TEST.CACHED");
+        assert_eq!(InstructionId::lookup("TEST.CACHED"), Some(id));
+        assert_eq!(InstructionId::cached(&ID, "TEST.CACHED"), id);
+    }
+
+    #[test]
+    fn cache_is_sorted_by_name() {
+        let mut instruction_set = InstructionSet::new();
+        instruction_set.add(String::from("TEST.CACHE*B"), Instruction::new(noop));
+        instruction_set.add(String::from("TEST.CACHE*A"), Instruction::new(noop));
+        let names: Vec<&str> = instruction_set.cache().list.iter().map(|id| id.name()).collect();
+        assert_eq!(names, vec!["TEST.CACHE*A", "TEST.CACHE*B"]);
+    }
+
+    #[test]
+    fn instructions_are_dispatched_by_id() {
+        let mut instruction_set = InstructionSet::new();
+        instruction_set.load();
+        let id = InstructionId::intern("INTEGER.+");
+        assert!(instruction_set.contains(id));
+        assert!(instruction_set.is_instruction("INTEGER.+"));
+        assert!(!instruction_set.contains(InstructionId::intern("TEST.UNKNOWN")));
+        assert!(!instruction_set.is_instruction("TEST.UNKNOWN"));
+        let mut push_state = PushState::new();
+        push_state.int_stack.push(2);
+        push_state.int_stack.push(3);
+        let icache = instruction_set.cache();
+        if let Some(instruction) = instruction_set.get_instruction_by_id(id) {
+            (instruction.execute)(&mut push_state, &icache);
+        }
+        assert_eq!(push_state.int_stack.to_string(), "5");
+        assert_eq!(icache.list.len(), instruction_set.ids().len());
+    }
+
+    #[test]
+    fn instruction_set_is_shared_between_threads() {
+        let mut instruction_set = InstructionSet::new();
+        instruction_set.load();
+        let instruction_set = std::sync::Arc::new(instruction_set);
+        let workers: Vec<_> = (0..4)
+            .map(|i| {
+                let instruction_set = std::sync::Arc::clone(&instruction_set);
+                std::thread::spawn(move || {
+                    let mut push_state = PushState::new();
+                    push_state.int_stack.push(i);
+                    push_state.int_stack.push(i);
+                    let icache = instruction_set.cache();
+                    if let Some(instruction) = instruction_set.get_instruction("INTEGER.*") {
+                        (instruction.execute)(&mut push_state, &icache);
+                    }
+                    push_state.int_stack.pop()
+                })
+            })
+            .collect();
+        let results: Vec<Option<i32>> = workers.into_iter().map(|w| w.join().unwrap()).collect();
+        assert_eq!(results, vec![Some(0), Some(1), Some(4), Some(9)]);
+    }
+}
@@ -67,43 +67,43 @@ pub fn integer_id(push_state: &mut PushState, _instruction_set: &InstructionCach
 
 /// INTEGER.%: Pushes the second stack item modulo the top stack item. If the top item is zero this
 /// acts as a NOOP. The modulus is computed as the remainder of the quotient, where the quotient
-/// has first been truncated toward negative infinity.
+/// has first been truncated toward negative infinity. Overflows wrap around.
 pub fn integer_modulus(push_state: &mut PushState, _instruction_set: &InstructionCache) {
     if let Some(ivals) = push_state.int_stack.pop_vec(2) {
         if ivals[1] != 0i32 {
-            push_state.int_stack.push(ivals[0] % ivals[1]);
+            push_state.int_stack.push(ivals[0].wrapping_rem(ivals[1]));
         }
     }
 }
 
-/// INTEGER.*: Pushes the product of the top two items.
+/// INTEGER.*: Pushes the product of the top two items. Overflows wrap around.
 fn integer_mult(push_state: &mut PushState, _instruction_cache: &InstructionCache) {
     if let Some(ivals) = push_state.int_stack.pop_vec(2) {
-        push_state.int_stack.push(ivals[0] * ivals[1]);
+        push_state.int_stack.push(ivals[0].wrapping_mul(ivals[1]));
     }
 }
 
-/// INTEGER.+: Pushes the sum of the top two items.
+/// INTEGER.+: Pushes the sum of the top two items. Overflows wrap around.
 fn integer_add(push_state: &mut PushState, _instruction_cache: &InstructionCache) {
     if let Some(ivals) = push_state.int_stack.pop_vec(2) {
-        push_state.int_stack.push(ivals[0] + ivals[1]);
+        push_state.int_stack.push(ivals[0].wrapping_add(ivals[1]));
     }
 }
 
 /// INTEGER.-: Pushes the difference of the top two items; that is, the second item minus the top
-/// item.
+/// item. Overflows wrap around.
 fn integer_subtract(push_state: &mut PushState, _instruction_cache: &InstructionCache) {
     if let Some(ivals) = push_state.int_stack.pop_vec(2) {
-        push_state.int_stack.push(ivals[0] - ivals[1]);
+        push_state.int_stack.push(ivals[0].wrapping_sub(ivals[1]));
     }
 }
 
 /// INTEGER./: Pushes the quotient of the top two items; that is, the second item divided by the
-/// top item. If the top item is zero this acts as a NOOP.
+/// top item. If the top item is zero this acts as a NOOP. Overflows wrap around.
 fn integer_divide(push_state: &mut PushState, _instruction_cache: &InstructionCache) {
     if let Some(ivals) = push_state.int_stack.pop_vec(2) {
         if ivals[1] != 0i32 {
-            push_state.int_stack.push(ivals[0] / ivals[1]);
+            push_state.int_stack.push(ivals[0].wrapping_div(ivals[1]));
         }
     }
 }
@@ -132,10 +132,11 @@ fn integer_greater(push_state: &mut PushState, _instruction_cache: &InstructionC
     }
 }
 
-/// INTEGER.ABS: Pushes the absolute value of the top INTEGER item.
+/// INTEGER.ABS: Pushes the absolute value of the top INTEGER item. The absolute value of the
+/// smallest integer wraps around to itself.
 fn integer_abs(push_state: &mut PushState, _instruction_cache: &InstructionCache) {
     if let Some(ival) = push_state.int_stack.pop() {
-        push_state.int_stack.push(i32::abs(ival));
+        push_state.int_stack.push(i32::wrapping_abs(ival));
     }
 }
 
@@ -144,7 +145,7 @@ fn integer_abs(push_state: &mut PushState, _instruction_cache: &InstructionCache
 pub fn integer_define(push_state: &mut PushState, _instruction_cache: &InstructionCache) {
     if let Some(name) = push_state.name_stack.pop() {
         if let Some(ival) = push_state.int_stack.pop() {
-            push_state.name_bindings.insert(name, Item::int(ival));
+            push_state.bind(name, Item::int(ival));
         }
     }
 }
@@ -311,6 +312,34 @@ mod tests {
         assert_eq!(test_state.int_stack.pop().unwrap(), 6);
     }
 
+    #[test]
+    fn integer_arithmetic_wraps_on_overflow() {
+        let mut test_state = PushState::new();
+        test_state.int_stack.push(i32::MAX);
+        test_state.int_stack.push(2);
+        integer_mult(&mut test_state, &icache());
+        assert_eq!(test_state.int_stack.pop().unwrap(), -2);
+        test_state.int_stack.push(i32::MIN);
+        test_state.int_stack.push(-1);
+        integer_divide(&mut test_state, &icache());
+        assert_eq!(test_state.int_stack.pop().unwrap(), i32::MIN);
+        test_state.int_stack.push(i32::MIN);
+        test_state.int_stack.push(-1);
+        integer_modulus(&mut test_state, &icache());
+        assert_eq!(test_state.int_stack.pop().unwrap(), 0);
+        test_state.int_stack.push(i32::MAX);
+        test_state.int_stack.push(1);
+        integer_add(&mut test_state, &icache());
+        assert_eq!(test_state.int_stack.pop().unwrap(), i32::MIN);
+        test_state.int_stack.push(i32::MIN);
+        test_state.int_stack.push(1);
+        integer_subtract(&mut test_state, &icache());
+        assert_eq!(test_state.int_stack.pop().unwrap(), i32::MAX);
+        test_state.int_stack.push(i32::MIN);
+        integer_abs(&mut test_state, &icache());
+        assert_eq!(test_state.int_stack.pop().unwrap(), i32::MIN);
+    }
+
     #[test]
     fn integer_subtract_pushes_result() {
         let mut test_state = PushState::new();
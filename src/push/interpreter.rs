@@ -1,16 +1,57 @@
 use crate::push::instructions::{InstructionCache, InstructionSet};
 use crate::push::item::{Item, PushType};
+use crate::push::journal::StateDelta;
 use crate::push::state::PushState;
 use std::time::{Duration, Instant};
 
 #[derive(Debug, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub enum PushInterpreterState {
     NoErrors,
     StepLimitExceeded,
     TimeLimitExceeded,
     GrowthCapExceeded,
+    BudgetExhausted,
 }
 
+/// Limits a single call to PushInterpreter::run_for. The execution stops
+/// after max_steps steps or when max_time has passed, whatever comes first.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct RunBudget {
+    pub max_steps: usize,
+    pub max_time: Duration,
+}
+
+impl RunBudget {
+    pub fn new(max_steps: usize, max_time: Duration) -> Self {
+        Self {
+            max_steps,
+            max_time,
+        }
+    }
+}
+
+/// Summary of a call to PushInterpreter::run or PushInterpreter::run_for.
+#[derive(Debug)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct RunReport {
+    // Reason for the termination of the run
+    pub state: PushInterpreterState,
+    // Number of steps executed by this call
+    pub steps: usize,
+    // Wall time of this call
+    pub elapsed: Duration,
+    // Largest value of PushState::size() at the end of any step
+    pub peak_size: usize,
+    // Number of instructions that had no effect, e.g. because arguments were missing
+    pub noop_instructions: usize,
+    // Step of the program at which a limit was exceeded or None if the run terminated
+    // without errors. For the step and time limit this is the step that has not been
+    // executed anymore. The budget of run_for is not a limit in this sense.
+    pub limit_step: Option<usize>,
+}
+
+
 pub struct PushInterpreter {}
 
 impl PushInterpreter {
@@ -23,28 +64,73 @@ impl PushInterpreter {
     }
 
     /// Executes one instruction from the top of the execution stack.
-    /// Returns true if the execution stack is empty.
+    /// Returns true if the execution stack is empty. The changes are
+    /// recorded if the state has a journal.
     pub fn step(
         push_state: &mut PushState,
-        instruction_set: &mut InstructionSet,
+        instruction_set: &InstructionSet,
         icache: &InstructionCache,
     ) -> bool {
-        match push_state.exec_stack.pop() {
-            None => true,
-            Some(Item::Literal { push_type }) => {
-                match push_type {
-                    PushType::Bool { val } => push_state.bool_stack.push(val),
-                    PushType::Int { val } => push_state.int_stack.push(val),
-                    PushType::Index { val } => push_state.index_stack.push(val),
-                    PushType::Float { val } => push_state.float_stack.push(val),
-                    PushType::BoolVector { val } => push_state.bool_vector_stack.push(val),
-                    PushType::FloatVector { val } => push_state.float_vector_stack.push(val),
-                    PushType::IntVector { val } => push_state.int_vector_stack.push(val),
-                    PushType::Graph { val } => push_state.graph_stack.push(val),
-                }
-                false
+        if push_state.exec_stack.size() == 0 {
+            return true;
+        }
+        let delta = PushInterpreter::begin_step(push_state);
+        let item = push_state.exec_stack.pop().unwrap();
+        PushInterpreter::execute(push_state, instruction_set, icache, item);
+        PushInterpreter::end_step(push_state, delta);
+        false
+    }
+
+    /// Reverts the most recent step recorded in the journal of the state.
+    /// Returns false if there is no journal or it is empty. Node ids are
+    /// assigned by a process wide counter that is not reverted, so graph
+    /// nodes that are added again after a step back get new ids.
+    pub fn step_back(push_state: &mut PushState) -> bool {
+        match push_state.journal.as_mut().and_then(|journal| journal.pop()) {
+            Some(delta) => {
+                delta.revert(push_state);
+                true
             }
-            Some(Item::Identifier { name }) => {
+            None => false,
+        }
+    }
+
+    fn begin_step(push_state: &mut PushState) -> Option<StateDelta> {
+        if push_state.journal.is_some() {
+            Some(StateDelta::begin(push_state))
+        } else {
+            None
+        }
+    }
+
+    fn end_step(push_state: &mut PushState, delta: Option<StateDelta>) {
+        if let Some(delta) = delta {
+            let delta = delta.end(push_state);
+            if let Some(journal) = &mut push_state.journal {
+                journal.push(delta);
+            }
+        }
+    }
+
+    /// Executes an item that has been popped from the execution stack.
+    pub fn execute(
+        push_state: &mut PushState,
+        instruction_set: &InstructionSet,
+        icache: &InstructionCache,
+        item: Item,
+    ) {
+        match item {
+            Item::Literal { push_type } => match push_type {
+                PushType::Bool { val } => push_state.bool_stack.push(val),
+                PushType::Int { val } => push_state.int_stack.push(val),
+                PushType::Index { val } => push_state.index_stack.push(val),
+                PushType::Float { val } => push_state.float_stack.push(val),
+                PushType::BoolVector { val } => push_state.bool_vector_stack.push(val),
+                PushType::FloatVector { val } => push_state.float_vector_stack.push(val),
+                PushType::IntVector { val } => push_state.int_vector_stack.push(val),
+                PushType::Graph { val } => push_state.graph_stack.push(val),
+            },
+            Item::Identifier { name } => {
                 if push_state.quote_name {
                     // Always push to name stack when quote_name flag is set
                     push_state.name_stack.push(name);
@@ -57,56 +143,185 @@ impl PushInterpreter {
                         push_state.name_stack.push(name);
                     }
                 }
-                false
             }
-            Some(Item::InstructionMeta { name }) => {
-                if let Some(instruction) = instruction_set.get_instruction(&name) {
+            Item::InstructionMeta { id } => {
+                if let Some(instruction) = instruction_set.get_instruction_by_id(id) {
                     (instruction.execute)(push_state, &icache);
                 }
-                false
             }
-            Some(Item::List { mut items }) => {
-                if let Some(pv) = items.pop_vec(items.size()) {
+            Item::List { items } => {
+                if let Some(pv) = items.copy_vec(items.size()) {
                     push_state.exec_stack.push_vec(pv);
                 }
-                false
             }
         }
     }
+
     /// Copies execution stack to code stac and recursively runs execution stack.
     /// Stops execution if Step Limit, Time Limit or Growth Cap are exceeded and
-    /// returns corresponding error code.
-    pub fn run(
+    /// returns a report including the corresponding error code.
+    pub fn run(push_state: &mut PushState, instruction_set: &InstructionSet) -> RunReport {
+        PushInterpreter::run_with_observer(push_state, instruction_set, &mut NoObserver {})
+    }
+
+    /// Same as run but notifies the observer before and after each executed step.
+    pub fn run_with_observer<O: PushObserver>(
+        push_state: &mut PushState,
+        instruction_set: &InstructionSet,
+        observer: &mut O,
+    ) -> RunReport {
+        push_state.started = false;
+        push_state.steps = 0;
+        push_state.elapsed = Duration::from_millis(0);
+        PushInterpreter::run_slice(push_state, instruction_set, observer, None)
+    }
+
+    /// Runs the program of the state until it terminates, a limit is exceeded or the
+    /// budget is exhausted. In the latter case the report state is BudgetExhausted and
+    /// calling run_for again continues the execution where it stopped. Step and time
+    /// limits of the configuration apply to the total of all calls.
+    pub fn run_for(
+        push_state: &mut PushState,
+        instruction_set: &InstructionSet,
+        budget: RunBudget,
+    ) -> RunReport {
+        PushInterpreter::run_for_with_observer(
+            push_state,
+            instruction_set,
+            budget,
+            &mut NoObserver {},
+        )
+    }
+
+    /// Same as run_for but notifies the observer before and after each executed step.
+    pub fn run_for_with_observer<O: PushObserver>(
+        push_state: &mut PushState,
+        instruction_set: &InstructionSet,
+        budget: RunBudget,
+        observer: &mut O,
+    ) -> RunReport {
+        PushInterpreter::run_slice(push_state, instruction_set, observer, Some(budget))
+    }
+
+    /// Continues the execution of the state. If the program has not been started
+    /// yet, the execution stack is copied to the code stack and the step count
+    /// and elapsed time are reset. A program ends when the execution stack is
+    /// empty, so that the next program pushed to the state starts afresh.
+    fn run_slice<O: PushObserver>(
         push_state: &mut PushState,
-        instruction_set: &mut InstructionSet,
-    ) -> PushInterpreterState {
-        PushInterpreter::copy_to_code_stack(push_state);
+        instruction_set: &InstructionSet,
+        observer: &mut O,
+        budget: Option<RunBudget>,
+    ) -> RunReport {
+        if !push_state.started && push_state.exec_stack.size() > 0 {
+            PushInterpreter::copy_to_code_stack(push_state);
+            push_state.started = true;
+            push_state.steps = 0;
+            push_state.elapsed = Duration::from_millis(0);
+        }
         let icache = instruction_set.cache();
-        let mut step_counter = 0;
+        let mut report = RunReport {
+            state: PushInterpreterState::NoErrors,
+            steps: 0,
+            elapsed: Duration::from_millis(0),
+            peak_size: push_state.size(),
+            noop_instructions: 0,
+            limit_step: None,
+        };
         let start = Instant::now();
+        let elapsed_before = push_state.elapsed;
         loop {
-            if step_counter > push_state.configuration.eval_push_limit {
-                return PushInterpreterState::StepLimitExceeded;
+            if push_state.steps as i32 > push_state.configuration.eval_push_limit {
+                report.state = PushInterpreterState::StepLimitExceeded;
+                report.limit_step = Some(push_state.steps);
+                break;
             }
-            if start.elapsed() > Duration::from_millis(push_state.configuration.eval_time_limit) {
-                return PushInterpreterState::TimeLimitExceeded;
+            if elapsed_before + start.elapsed()
+                > Duration::from_millis(push_state.configuration.eval_time_limit)
+            {
+                report.state = PushInterpreterState::TimeLimitExceeded;
+                report.limit_step = Some(push_state.steps);
+                break;
             }
-            let size_before_step = push_state.size();
-            if PushInterpreter::step(push_state, instruction_set, &icache) {
+            if let Some(budget) = budget {
+                if report.steps >= budget.max_steps || start.elapsed() >= budget.max_time {
+                    if push_state.exec_stack.size() > 0 {
+                        report.state = PushInterpreterState::BudgetExhausted;
+                    }
+                    break;
+                }
+            }
+            if push_state.exec_stack.size() == 0 {
                 break;
             }
+            let delta = PushInterpreter::begin_step(push_state);
+            let size_before_step = push_state.size();
+            let item = push_state.exec_stack.pop().unwrap();
+            observer.before_step(push_state.steps, &item, push_state);
+            let is_instruction = matches!(item, Item::InstructionMeta { id: _ });
+            // Literals are moved to their stacks, so only copy them if the observer needs them
+            let observed_item = if observer.observes_after_step() {
+                Some(item.clone())
+            } else {
+                None
+            };
+            let changes_before_step = push_state.change_mark();
+            PushInterpreter::execute(push_state, instruction_set, &icache, item);
+            if is_instruction && changes_before_step == push_state.change_mark() {
+                report.noop_instructions += 1;
+            }
+            if let Some(observed_item) = observed_item {
+                observer.after_step(push_state.steps, &observed_item, push_state);
+            }
+            report.peak_size = usize::max(report.peak_size, push_state.size());
+            push_state.steps += 1;
+            report.steps += 1;
+            PushInterpreter::end_step(push_state, delta);
             if push_state.size() > size_before_step + push_state.configuration.growth_cap as usize {
-                return PushInterpreterState::GrowthCapExceeded;
+                report.state = PushInterpreterState::GrowthCapExceeded;
+                report.limit_step = Some(push_state.steps - 1);
+                break;
             }
-            step_counter += 1;
         }
-        PushInterpreterState::NoErrors
+        report.elapsed = start.elapsed();
+        push_state.elapsed = elapsed_before + report.elapsed;
+        if push_state.exec_stack.size() == 0 {
+            push_state.started = false;
+        }
+        report
+    }
+}
+
+/// Receives callbacks from PushInterpreter::run_with_observer for every executed step.
+/// The step number starts at zero and the item is the one that has been popped from
+/// the EXEC stack. Both methods do nothing by default.
+pub trait PushObserver {
+    /// Called before the popped item is executed.
+    fn before_step(&mut self, _step: usize, _item: &Item, _push_state: &PushState) {}
+
+    /// Called after the popped item has been executed.
+    fn after_step(&mut self, _step: usize, _item: &Item, _push_state: &PushState) {}
+
+    /// Returns false if after_step does not need to be called. The interpreter
+    /// then does not keep a copy of the executed item.
+    fn observes_after_step(&self) -> bool {
+        true
+    }
+}
+
+/// Observer that ignores all callbacks.
+pub struct NoObserver {}
+
+impl PushObserver for NoObserver {
+    fn observes_after_step(&self) -> bool {
+        false
     }
 }
 
 #[cfg(test)]
 mod tests {
     use super::*;
+    use crate::push::journal::Journal;
     use crate::push::parser::PushParser;
 
     pub fn icache() -> InstructionCache {
@@ -150,7 +365,7 @@ mod tests {
         assert_eq!(push_state.exec_stack.to_string(), "2 3 INTEGER.* 4.100 5.200 FLOAT.+ TRUE FALSE BOOLEAN.OR");
 
         assert_eq!(
-            PushInterpreter::run(&mut push_state, &mut instruction_set),
+            PushInterpreter::run(&mut push_state, &instruction_set).state,
             PushInterpreterState::NoErrors
         );
         assert_eq!(push_state.int_stack.to_string(), "6");
@@ -158,6 +373,229 @@ mod tests {
         assert_eq!(push_state.bool_stack.to_string(), "TRUE");
     }
 
+    struct StepRecorder {
+        before: Vec<(usize, String)>,
+        after: Vec<(usize, String)>,
+        int_sizes: Vec<usize>,
+    }
+
+    impl PushObserver for StepRecorder {
+        fn before_step(&mut self, step: usize, item: &Item, _push_state: &PushState) {
+            self.before.push((step, item.to_string()));
+        }
+
+        fn after_step(&mut self, step: usize, item: &Item, push_state: &PushState) {
+            self.after.push((step, item.to_string()));
+            self.int_sizes.push(push_state.int_stack.size());
+        }
+    }
+
+    struct BeforeStepRecorder {
+        before: Vec<usize>,
+    }
+
+    impl PushObserver for BeforeStepRecorder {
+        fn before_step(&mut self, step: usize, _item: &Item, _push_state: &PushState) {
+            self.before.push(step);
+        }
+
+        fn after_step(&mut self, _step: usize, _item: &Item, _push_state: &PushState) {
+            panic!("after_step must not be called");
+        }
+
+        fn observes_after_step(&self) -> bool {
+            false
+        }
+    }
+
+    #[test]
+    pub fn run_with_observer_skips_after_step_if_not_observed() {
+        let mut push_state = PushState::new();
+        let mut instruction_set = InstructionSet::new();
+        instruction_set.load();
+        PushParser::parse_program(&mut push_state, &instruction_set, "( INT[1,2] INTEGER.+ )");
+        let mut recorder = BeforeStepRecorder { before: vec![] };
+        let report =
+            PushInterpreter::run_with_observer(&mut push_state, &instruction_set, &mut recorder);
+        assert_eq!(recorder.before, vec![0, 1, 2]);
+        assert_eq!(report.noop_instructions, 1);
+        assert_eq!(push_state.int_vector_stack.to_string(), "[1,2]");
+    }
+
+    #[test]
+    pub fn run_with_observer_reports_each_step() {
+        let input = "( 2 3 INTEGER.* )";
+        let mut push_state = PushState::new();
+        let mut instruction_set = InstructionSet::new();
+        instruction_set.load();
+        PushParser::parse_program(&mut push_state, &instruction_set, &input);
+        let mut recorder = StepRecorder {
+            before: vec![],
+            after: vec![],
+            int_sizes: vec![],
+        };
+        assert_eq!(
+            PushInterpreter::run_with_observer(&mut push_state, &instruction_set, &mut recorder)
+                .state,
+            PushInterpreterState::NoErrors
+        );
+        let expected = vec![
+            (0, "( 2 3 INTEGER.* )".to_string()),
+            (1, "2".to_string()),
+            (2, "3".to_string()),
+            (3, "INTEGER.*".to_string()),
+        ];
+        assert_eq!(recorder.before, expected);
+        assert_eq!(recorder.after, expected);
+        assert_eq!(recorder.int_sizes, vec![0, 1, 2, 1]);
+        assert_eq!(push_state.int_stack.to_string(), "6");
+    }
+
+    #[test]
+    pub fn run_with_observer_applies_step_limit() {
+        let input = "( EXEC.Y ( 1 ) )";
+        let mut push_state = PushState::new();
+        let mut instruction_set = InstructionSet::new();
+        instruction_set.load();
+        push_state.configuration.eval_push_limit = 10;
+        PushParser::parse_program(&mut push_state, &instruction_set, &input);
+        let mut recorder = StepRecorder {
+            before: vec![],
+            after: vec![],
+            int_sizes: vec![],
+        };
+        let report =
+            PushInterpreter::run_with_observer(&mut push_state, &instruction_set, &mut recorder);
+        assert_eq!(report.state, PushInterpreterState::StepLimitExceeded);
+        assert_eq!(report.steps, 11);
+        assert_eq!(report.limit_step, Some(11));
+        assert_eq!(recorder.before.len(), 11);
+        assert_eq!(recorder.after.len(), 11);
+    }
+
+    #[test]
+    pub fn run_reports_steps_size_and_noops() {
+        let input = "( 2 3 INTEGER.* INTEGER.+ BOOLEAN.AND )";
+        let mut push_state = PushState::new();
+        let mut instruction_set = InstructionSet::new();
+        instruction_set.load();
+        PushParser::parse_program(&mut push_state, &instruction_set, &input);
+        let report = PushInterpreter::run(&mut push_state, &instruction_set);
+        assert_eq!(report.state, PushInterpreterState::NoErrors);
+        assert_eq!(report.steps, 6);
+        assert_eq!(report.peak_size, 6);
+        assert_eq!(report.noop_instructions, 2);
+        assert_eq!(report.limit_step, None);
+    }
+
+    #[test]
+    pub fn run_reports_step_of_exceeded_growth_cap() {
+        let input = "( 1 2 3 4 5 )";
+        let mut push_state = PushState::new();
+        let mut instruction_set = InstructionSet::new();
+        instruction_set.load();
+        push_state.configuration.growth_cap = 2;
+        PushParser::parse_program(&mut push_state, &instruction_set, &input);
+        let report = PushInterpreter::run(&mut push_state, &instruction_set);
+        assert_eq!(report.state, PushInterpreterState::GrowthCapExceeded);
+        assert_eq!(report.steps, 1);
+        assert_eq!(report.limit_step, Some(0));
+    }
+
+    #[test]
+    pub fn run_for_continues_where_it_stopped() {
+        let input = "( 1 2 3 INTEGER.+ INTEGER.+ )";
+        let mut push_state = PushState::new();
+        let mut instruction_set = InstructionSet::new();
+        instruction_set.load();
+        PushParser::parse_program(&mut push_state, &instruction_set, &input);
+        let budget = RunBudget::new(2, Duration::from_secs(10));
+        let report = PushInterpreter::run_for(&mut push_state, &instruction_set, budget);
+        assert_eq!(report.state, PushInterpreterState::BudgetExhausted);
+        assert_eq!(report.steps, 2);
+        assert_eq!(push_state.int_stack.to_string(), "1");
+        let report = PushInterpreter::run_for(&mut push_state, &instruction_set, budget);
+        assert_eq!(report.state, PushInterpreterState::BudgetExhausted);
+        assert_eq!(push_state.int_stack.to_string(), "3 2 1");
+        let report = PushInterpreter::run_for(&mut push_state, &instruction_set, budget);
+        assert_eq!(report.state, PushInterpreterState::NoErrors);
+        assert_eq!(report.steps, 2);
+        assert_eq!(push_state.steps, 6);
+        assert_eq!(push_state.int_stack.to_string(), "6");
+        assert_eq!(push_state.code_stack.to_string(), "( 1 2 3 INTEGER.+ INTEGER.+ )");
+    }
+
+    #[test]
+    pub fn run_for_starts_next_program_afresh() {
+        let mut push_state = PushState::new();
+        let mut instruction_set = InstructionSet::new();
+        instruction_set.load();
+        let budget = RunBudget::new(100, Duration::from_secs(10));
+        PushParser::parse_program(&mut push_state, &instruction_set, "( 1 2 INTEGER.+ )");
+        PushInterpreter::run_for(&mut push_state, &instruction_set, budget);
+        assert!(!push_state.started);
+        assert_eq!(push_state.steps, 4);
+        push_state.code_stack.flush();
+        PushParser::parse_program(&mut push_state, &instruction_set, "( 5 INTEGER.* )");
+        let report = PushInterpreter::run_for(&mut push_state, &instruction_set, budget);
+        assert_eq!(report.state, PushInterpreterState::NoErrors);
+        assert_eq!(push_state.code_stack.to_string(), "( 5 INTEGER.* )");
+        assert_eq!(push_state.steps, 3);
+        assert_eq!(push_state.int_stack.to_string(), "15");
+    }
+
+    #[test]
+    pub fn run_for_applies_step_limit_across_calls() {
+        let input = "( EXEC.Y ( 1 ) )";
+        let mut push_state = PushState::new();
+        let mut instruction_set = InstructionSet::new();
+        instruction_set.load();
+        push_state.configuration.eval_push_limit = 10;
+        PushParser::parse_program(&mut push_state, &instruction_set, &input);
+        let budget = RunBudget::new(4, Duration::from_secs(10));
+        for _ in 0..2 {
+            assert_eq!(
+                PushInterpreter::run_for(&mut push_state, &instruction_set, budget).state,
+                PushInterpreterState::BudgetExhausted
+            );
+        }
+        let report = PushInterpreter::run_for(&mut push_state, &instruction_set, budget);
+        assert_eq!(report.state, PushInterpreterState::StepLimitExceeded);
+        assert_eq!(report.steps, 3);
+        assert_eq!(report.limit_step, Some(11));
+    }
+
+    #[test]
+    pub fn step_back_restores_previous_states() {
+        let input = "( 2 EXEC.DUP ( INTEGER.DUP INTEGER.* ) INTEGER.RAND )";
+        let mut push_state = PushState::with_seed(11);
+        let mut instruction_set = InstructionSet::new();
+        instruction_set.load();
+        push_state.journal = Some(Journal::new(100));
+        PushParser::parse_program(&mut push_state, &instruction_set, &input);
+        // The copy to the CODE stack at the start is not part of a step
+        PushInterpreter::copy_to_code_stack(&mut push_state);
+        push_state.started = true;
+        let mut states = vec![];
+        let budget = RunBudget::new(1, Duration::from_secs(10));
+        loop {
+            states.push(push_state.to_string());
+            let report = PushInterpreter::run_for(&mut push_state, &instruction_set, budget);
+            if report.state != PushInterpreterState::BudgetExhausted {
+                break;
+            }
+        }
+        let final_state = push_state.to_string();
+        assert_eq!(push_state.journal.as_ref().unwrap().len(), states.len());
+        while let Some(state) = states.pop() {
+            assert!(PushInterpreter::step_back(&mut push_state));
+            assert_eq!(push_state.to_string(), state);
+        }
+        assert!(!PushInterpreter::step_back(&mut push_state));
+        PushInterpreter::run_for(&mut push_state, &instruction_set, RunBudget::new(100, Duration::from_secs(10)));
+        assert_eq!(push_state.to_string(), final_state);
+    }
+
     #[test]
     pub fn run_potentiation_program() {
         let input = "( ARG FLOAT.DEFINE EXEC.Y ( ARG FLOAT.* 1 INTEGER.- INTEGER.DUP 0 INTEGER.> EXEC.IF ( ) EXEC.POP ) ) ";
@@ -168,7 +606,7 @@ mod tests {
         push_state.int_stack.push(4);
         push_state.float_stack.push(2.0);
         assert_eq!(
-            PushInterpreter::run(&mut push_state, &mut instruction_set),
+            PushInterpreter::run(&mut push_state, &instruction_set).state,
             PushInterpreterState::NoErrors
         );
         assert_eq!(push_state.float_stack.to_string(), "16.0");
@@ -185,7 +623,7 @@ mod tests {
         PushParser::parse_program(&mut push_state, &instruction_set, &input);
         push_state.int_stack.push(4);
         assert_eq!(
-            PushInterpreter::run(&mut push_state, &mut instruction_set),
+            PushInterpreter::run(&mut push_state, &instruction_set).state,
             PushInterpreterState::NoErrors
         );
         assert_eq!(push_state.int_stack.to_string(), "24");
@@ -200,7 +638,7 @@ mod tests {
         instruction_set.load();
         PushParser::parse_program(&mut push_state, &instruction_set, &input);
         loop {
-            if PushInterpreter::step(&mut push_state, &mut instruction_set, &icache()) {
+            if PushInterpreter::step(&mut push_state, &instruction_set, &icache()) {
                 break;
             }
         }
@@ -218,7 +656,7 @@ mod tests {
         instruction_set.load();
         PushParser::parse_program(&mut push_state, &instruction_set, &input);
         loop {
-            if PushInterpreter::step(&mut push_state, &mut instruction_set, &icache()) {
+            if PushInterpreter::step(&mut push_state, &instruction_set, &icache()) {
                 break;
             }
         }
@@ -236,7 +674,7 @@ mod tests {
         instruction_set.load();
         PushParser::parse_program(&mut push_state, &instruction_set, &input);
         loop {
-            if PushInterpreter::step(&mut push_state, &mut instruction_set, &icache()) {
+            if PushInterpreter::step(&mut push_state, &instruction_set, &icache()) {
                 break;
             }
         }
@@ -253,7 +691,7 @@ mod tests {
         instruction_set.load();
         PushParser::parse_program(&mut push_state, &instruction_set, &input);
         loop {
-            if PushInterpreter::step(&mut push_state, &mut instruction_set, &icache()) {
+            if PushInterpreter::step(&mut push_state, &instruction_set, &icache()) {
                 break;
             }
         }
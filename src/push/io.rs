@@ -7,6 +7,7 @@ use std::collections::HashMap;
 use std::fmt;
 
 #[derive(Clone, Debug, Default)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub struct PushMessage {
     pub header: IntVector,
     pub body: BoolVector,
@@ -1,21 +1,29 @@
 use std::fmt;
+use std::sync::Arc;
 
 use crate::push::graph::Graph;
 use crate::push::index::Index;
+use crate::push::instructions::InstructionId;
 use crate::push::stack::{PushStack, PushPrint};
 use crate::push::vector::{BoolVector, FloatVector, IntVector};
+use std::sync::OnceLock;
+
+// Id of the instruction that is returned by Item::noop
+static NOOP_ID: OnceLock<InstructionId> = OnceLock::new();
 
 // Items
 #[allow(dead_code)]
 #[derive(Clone, Debug)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub enum Item {
-    List { items: PushStack<Item> },
-    InstructionMeta { name: String },
+    List { items: Arc<PushStack<Item>> },
+    InstructionMeta { id: InstructionId },
     Literal { push_type: PushType },
     Identifier { name: String },
 }
 
 #[derive(Clone, PartialEq, Debug)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub enum PushType {
     Bool { val: bool },
     Int { val: i32 },
@@ -75,7 +83,9 @@ impl Item {
     }
 
     pub fn instruction(arg: String) -> Item {
-        Item::InstructionMeta { name: arg }
+        Item::InstructionMeta {
+            id: InstructionId::intern(&arg),
+        }
     }
 
     pub fn name(arg: String) -> Item {
@@ -84,17 +94,17 @@ impl Item {
 
     pub fn noop() -> Item {
         Item::InstructionMeta {
-            name: "NOOP".to_string(),
+            id: InstructionId::cached(&NOOP_ID, "NOOP"),
         }
     }
     pub fn empty_list() -> Item {
         Item::List {
-            items: PushStack::new(),
+            items: Arc::new(PushStack::new()),
         }
     }
     pub fn list(arg: Vec<Item>) -> Item {
         Item::List {
-            items: PushStack::from_vec(arg),
+            items: Arc::new(PushStack::from_vec(arg)),
         }
     }
     pub fn id(arg: String) -> Item {
@@ -152,20 +162,27 @@ impl Item {
     }
 
     /// Replaces a nested element of a list using depth first traversal.
+    /// Only the lists on the path to the replaced element are copied.
     pub fn insert(item: &mut Item, new_el: &Item, mut depth: usize) -> Result<bool, usize> {
         if depth == 0 {
             Ok(true)
         } else {
             match &mut *item {
                 Item::List { items } => {
-                    let replace_idx = depth - 1;
                     for i in 0..items.size() {
                         depth -= 1;
+                        let element_size = Item::size(items.get(i).unwrap());
+                        if depth >= element_size {
+                            // Replaced element is not part of this element
+                            depth -= element_size - 1;
+                            continue;
+                        }
+                        let items = Arc::make_mut(items);
                         let next = Item::insert(items.get_mut(i).unwrap(), new_el, depth);
                         match next {
                             Ok(replace_here) => {
                                 if replace_here {
-                                    let _ = items.replace(replace_idx, new_el.clone());
+                                    let _ = items.replace(i, new_el.clone());
                                 }
                                 return Ok(false);
                             }
@@ -179,18 +196,58 @@ impl Item {
         }
     }
 
+    /// Returns a copy of item where the element at the depth first position point
+    /// is replaced by new_el or None if item has less points. Position 0 replaces
+    /// the item itself. Uses the same positions as traverse.
+    pub fn replace_at(item: &Item, point: usize, new_el: &Item) -> Option<Item> {
+        if point == 0 {
+            return Some(new_el.clone());
+        }
+        let mut replaced = item.clone();
+        match Item::insert(&mut replaced, new_el, point) {
+            Ok(_) => Some(replaced),
+            Err(_) => None,
+        }
+    }
+
+    /// Returns a copy of item where the element at the depth first position point
+    /// is removed from its list or None if point is 0 or item has less points.
+    /// Only the lists on the path to the removed element are copied.
+    pub fn remove_at(item: &Item, point: usize) -> Option<Item> {
+        if point == 0 {
+            return None;
+        }
+        if let Item::List { items } = item {
+            // Position relative to the current element
+            let mut offset = point - 1;
+            for i in 0..items.size() {
+                let element_size = Item::size(items.get(i).unwrap());
+                if offset < element_size {
+                    let mut removed = items.clone();
+                    if offset == 0 {
+                        Arc::make_mut(&mut removed).remove(i);
+                    } else {
+                        let element = Item::remove_at(items.get(i).unwrap(), offset)?;
+                        let _ = Arc::make_mut(&mut removed).replace(i, element);
+                    }
+                    return Some(Item::List { items: removed });
+                }
+                offset -= element_size;
+            }
+        }
+        None
+    }
+
     /// Substitute all occurrences of 'pattern' with 'substitute' in 'item' using depth first
-    /// traversal.
+    /// traversal. Only the lists that contain the pattern are copied.
     pub fn substitute(item: &mut Item, pattern: &Item, substitute: &Item) -> bool {
         if Item::equals(item, pattern) {
             return true;
         } else {
             match &mut *item {
                 Item::List { items } => {
-                    for i in 0..items.size() {
-                        if Item::substitute(items.get_mut(i).unwrap(), pattern, substitute) {
-                            let _ = items.replace(i, substitute.clone());
-                        }
+                    if let Some(substituted) = Item::substitute_items(items, pattern, substitute) {
+                        *items = Arc::new(substituted);
                     }
                 }
                 _ => (),
@@ -199,6 +256,38 @@ impl Item {
         }
     }
 
+    /// Returns a copy of the list items with all occurrences of 'pattern' replaced or None
+    /// if the pattern is not part of the list. Unchanged sublists are shared with the original.
+    fn substitute_items(
+        items: &PushStack<Item>,
+        pattern: &Item,
+        substitute: &Item,
+    ) -> Option<PushStack<Item>> {
+        let mut substituted: Option<PushStack<Item>> = None;
+        for i in 0..items.size() {
+            let element = items.get(i).unwrap();
+            let replacement = if Item::equals(element, pattern) {
+                Some(substitute.clone())
+            } else {
+                match element {
+                    Item::List { items: sublist } => {
+                        Item::substitute_items(sublist, pattern, substitute).map(|sublist| {
+                            Item::List {
+                                items: Arc::new(sublist),
+                            }
+                        })
+                    }
+                    _ => None,
+                }
+            };
+            if let Some(replacement) = replacement {
+                let copy = substituted.get_or_insert_with(|| items.clone());
+                let _ = copy.replace(i, replacement);
+            }
+        }
+        substituted
+    }
+
     /// Returns the position of pattern within item or Err if pattern is not
     /// part of item
     pub fn contains(item: &Item, pattern: &Item, mut depth: usize) -> Result<usize, ()> {
@@ -289,8 +378,8 @@ impl Item {
                 }
                 _ => false,
             },
-            Item::InstructionMeta { name } => match pattern {
-                Item::InstructionMeta { name: pname } => name == pname,
+            Item::InstructionMeta { id } => match pattern {
+                Item::InstructionMeta { id: pid } => id == pid,
                 _ => false,
             },
             Item::Literal { push_type } => match pattern {
@@ -314,8 +403,8 @@ impl PartialEq for Item {
                 Item::List { items: _ } => return true,
                 _ => return false,
             },
-            Item::InstructionMeta { name: _ } => match &*other {
-                Item::InstructionMeta { name: _ } => return true,
+            Item::InstructionMeta { id: _ } => match &*other {
+                Item::InstructionMeta { id: _ } => return true,
                 _ => return false,
             },
             Item::Literal {
@@ -379,8 +468,8 @@ impl fmt::Display for Item {
     fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
         match &*self {
             Item::List { items } => write!(f, "( {} )", items.to_string()),
-            Item::InstructionMeta { name } => {
-                write!(f, "{}", name)
+            Item::InstructionMeta { id } => {
+                write!(f, "{}", id)
             }
             Item::Literal { push_type } => {
                 let info;
@@ -404,7 +493,8 @@ impl fmt::Display for Item {
 }
 
 impl PushType {
-    /// Returns true if type and value are equal
+    /// Returns true if type and value are equal. NaN floats are equal to each
+    /// other so that printed items parse back to equal items.
     pub fn equals(&self, other: &PushType) -> bool {
         match &*self {
             PushType::Bool { val } => match &*other {
@@ -420,7 +510,7 @@ impl PushType {
                 _ => false,
             },
             PushType::Float { val } => match &*other {
-                PushType::Float { val: other_val } => return val == other_val,
+                PushType::Float { val: other_val } => return float_equals(*val, *other_val),
                 _ => false,
             },
             PushType::BoolVector { val } => match &*other {
@@ -428,7 +518,14 @@ impl PushType {
                 _ => false,
             },
             PushType::FloatVector { val } => match &*other {
-                PushType::FloatVector { val: other_val } => return val == other_val,
+                PushType::FloatVector { val: other_val } => {
+                    return val.values.len() == other_val.values.len()
+                        && val
+                            .values
+                            .iter()
+                            .zip(&other_val.values)
+                            .all(|(a, b)| float_equals(*a, *b))
+                }
                 _ => false,
             },
             PushType::IntVector { val } => match &*other {
@@ -443,6 +540,10 @@ impl PushType {
     }
 }
 
+fn float_equals(a: f32, b: f32) -> bool {
+    a == b || (a.is_nan() && b.is_nan())
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -454,9 +555,7 @@ mod tests {
         let list_a = Item::list(vec![Item::float(3.4)]);
         let list_b = Item::list(vec![Item::int(0)]);
         let inst_a = Item::noop();
-        let inst_b = Item::InstructionMeta {
-            name: "BOOLEAN.AND".to_string(),
-        };
+        let inst_b = Item::instruction("BOOLEAN.AND".to_string());
         assert_eq!(list_a, list_b);
         assert_eq!(inst_a, inst_b);
         assert_eq!(literal_a, literal_b);
@@ -530,6 +629,43 @@ mod tests {
         assert_eq!(Item::insert(&mut test_item, &item_to_insert, 4), Err(4));
     }
 
+    #[test]
+    fn remove_at_uses_positions_of_traverse() {
+        let test_item = Item::list(vec![
+            Item::int(3),
+            Item::list(vec![Item::int(2), Item::int(1)]),
+            Item::int(0),
+        ]);
+        assert_eq!(test_item.to_string(), "( 0 ( 1 2 ) 3 )");
+        assert_eq!(
+            Item::remove_at(&test_item, 1).unwrap().to_string(),
+            "( ( 1 2 ) 3 )"
+        );
+        assert_eq!(Item::remove_at(&test_item, 2).unwrap().to_string(), "( 0 3 )");
+        assert_eq!(
+            Item::remove_at(&test_item, 4).unwrap().to_string(),
+            "( 0 ( 1 ) 3 )"
+        );
+        assert_eq!(Item::remove_at(&test_item, 5).unwrap().to_string(), "( 0 ( 1 2 ) )");
+        assert!(Item::remove_at(&test_item, 0).is_none());
+        assert!(Item::remove_at(&test_item, 6).is_none());
+    }
+
+    #[test]
+    fn replace_at_uses_positions_of_traverse() {
+        let test_item = Item::list(vec![
+            Item::int(4),
+            Item::list(vec![Item::int(3)]),
+            Item::int(2),
+        ]);
+        for point in 0..Item::size(&test_item) {
+            let replaced = Item::replace_at(&test_item, point, &Item::int(99)).unwrap();
+            assert_eq!(Item::traverse(&replaced, point).unwrap().to_string(), "99");
+        }
+        assert_eq!(test_item.to_string(), "( 2 ( 3 ) 4 )");
+        assert!(Item::replace_at(&test_item, 5, &Item::int(99)).is_none());
+    }
+
     #[test]
     fn size_includes_nested_lists_in_count() {
         let test_item = Item::list(vec![
@@ -658,4 +794,41 @@ mod tests {
             "( 1 2 9 4 )"
         );
     }
+
+    #[test]
+    fn substitute_copies_only_modified_lists() {
+        let original = Item::list(vec![
+            Item::list(vec![Item::int(5)]),
+            Item::list(vec![Item::int(3)]),
+        ]);
+        let mut test_item = original.clone();
+        Item::substitute(&mut test_item, &Item::int(3), &Item::int(9));
+        assert_eq!(original.to_string(), "( ( 3 ) ( 5 ) )");
+        assert_eq!(test_item.to_string(), "( ( 9 ) ( 5 ) )");
+        match (&original, &test_item) {
+            (Item::List { items: original_items }, Item::List { items: test_items }) => {
+                assert!(!Arc::ptr_eq(original_items, test_items));
+                match (original_items.get(1), test_items.get(1)) {
+                    (Some(Item::List { items: a }), Some(Item::List { items: b })) => {
+                        assert!(Arc::ptr_eq(a, b))
+                    }
+                    _ => assert!(false),
+                }
+            }
+            _ => assert!(false),
+        }
+    }
+
+    #[test]
+    fn substitute_without_match_keeps_list() {
+        let original = Item::list(vec![Item::list(vec![Item::int(5)]), Item::int(1)]);
+        let mut test_item = original.clone();
+        assert!(!Item::substitute(&mut test_item, &Item::int(3), &Item::int(9)));
+        match (&original, &test_item) {
+            (Item::List { items: original_items }, Item::List { items: test_items }) => {
+                assert!(Arc::ptr_eq(original_items, test_items))
+            }
+            _ => assert!(false),
+        }
+    }
 }
@@ -0,0 +1,247 @@
+use crate::push::buffer::BufferDelta;
+use crate::push::graph::Graph;
+use crate::push::index::Index;
+use crate::push::io::PushMessage;
+use crate::push::item::Item;
+use crate::push::stack::StackDelta;
+use crate::push::state::PushState;
+use crate::push::vector::{BoolVector, FloatVector, IntVector};
+use std::collections::VecDeque;
+use std::time::Duration;
+
+/// Changes of a single step that are needed to restore the previous state.
+/// Only the modified elements of the stacks and buffers are stored, e.g.
+/// a step that pushes an integer does not copy the graph stack. The counter
+/// of the graph node ids is shared by all states and is not restored.
+pub struct StateDelta {
+    bool_stack: Option<StackDelta<bool>>,
+    code_stack: Option<StackDelta<Item>>,
+    exec_stack: Option<StackDelta<Item>>,
+    float_stack: Option<StackDelta<f32>>,
+    index_stack: Option<StackDelta<Index>>,
+    int_stack: Option<StackDelta<i32>>,
+    name_stack: Option<StackDelta<String>>,
+    bool_vector_stack: Option<StackDelta<BoolVector>>,
+    float_vector_stack: Option<StackDelta<FloatVector>>,
+    int_vector_stack: Option<StackDelta<IntVector>>,
+    input_stack: Option<BufferDelta<PushMessage>>,
+    output_stack: Option<BufferDelta<PushMessage>>,
+    graph_stack: Option<BufferDelta<Graph>>,
+    // Previous values of the changed bindings in the order of the changes
+    name_bindings: Vec<(String, Option<Item>)>,
+    quote_name: bool,
+    send_name: bool,
+    started: bool,
+    steps: usize,
+    elapsed: Duration,
+    // Position of the random number generator in its stream
+    rng_position: u128,
+}
+
+impl StateDelta {
+    /// Starts recording the changes of the state. The returned delta is
+    /// completed by calling end after the step.
+    pub fn begin(push_state: &mut PushState) -> StateDelta {
+        push_state.bool_stack.begin_delta();
+        push_state.code_stack.begin_delta();
+        push_state.exec_stack.begin_delta();
+        push_state.float_stack.begin_delta();
+        push_state.index_stack.begin_delta();
+        push_state.int_stack.begin_delta();
+        push_state.name_stack.begin_delta();
+        push_state.bool_vector_stack.begin_delta();
+        push_state.float_vector_stack.begin_delta();
+        push_state.int_vector_stack.begin_delta();
+        push_state.input_stack.begin_delta();
+        push_state.output_stack.begin_delta();
+        push_state.graph_stack.begin_delta();
+        push_state.binding_delta = Some(vec![]);
+        StateDelta {
+            bool_stack: None,
+            code_stack: None,
+            exec_stack: None,
+            float_stack: None,
+            index_stack: None,
+            int_stack: None,
+            name_stack: None,
+            bool_vector_stack: None,
+            float_vector_stack: None,
+            int_vector_stack: None,
+            input_stack: None,
+            output_stack: None,
+            graph_stack: None,
+            name_bindings: vec![],
+            quote_name: push_state.quote_name,
+            send_name: push_state.send_name,
+            started: push_state.started,
+            steps: push_state.steps,
+            elapsed: push_state.elapsed,
+            rng_position: push_state.rng.get_word_pos(),
+        }
+    }
+
+    /// Stops recording and collects the changes since begin.
+    pub fn end(mut self, push_state: &mut PushState) -> StateDelta {
+        self.bool_stack = push_state.bool_stack.end_delta();
+        self.code_stack = push_state.code_stack.end_delta();
+        self.exec_stack = push_state.exec_stack.end_delta();
+        self.float_stack = push_state.float_stack.end_delta();
+        self.index_stack = push_state.index_stack.end_delta();
+        self.int_stack = push_state.int_stack.end_delta();
+        self.name_stack = push_state.name_stack.end_delta();
+        self.bool_vector_stack = push_state.bool_vector_stack.end_delta();
+        self.float_vector_stack = push_state.float_vector_stack.end_delta();
+        self.int_vector_stack = push_state.int_vector_stack.end_delta();
+        self.input_stack = push_state.input_stack.end_delta();
+        self.output_stack = push_state.output_stack.end_delta();
+        self.graph_stack = push_state.graph_stack.end_delta();
+        self.name_bindings = push_state.binding_delta.take().unwrap_or_default();
+        self
+    }
+
+    /// Restores the state before the step. The state must not have been
+    /// modified since the delta has been recorded except by later steps
+    /// whose deltas have already been reverted.
+    pub fn revert(self, push_state: &mut PushState) {
+        fn revert_stack<T, F: FnOnce(T)>(delta: Option<T>, revert: F) {
+            if let Some(delta) = delta {
+                revert(delta);
+            }
+        }
+        revert_stack(self.bool_stack, |d| push_state.bool_stack.revert(d));
+        revert_stack(self.code_stack, |d| push_state.code_stack.revert(d));
+        revert_stack(self.exec_stack, |d| push_state.exec_stack.revert(d));
+        revert_stack(self.float_stack, |d| push_state.float_stack.revert(d));
+        revert_stack(self.index_stack, |d| push_state.index_stack.revert(d));
+        revert_stack(self.int_stack, |d| push_state.int_stack.revert(d));
+        revert_stack(self.name_stack, |d| push_state.name_stack.revert(d));
+        revert_stack(self.bool_vector_stack, |d| {
+            push_state.bool_vector_stack.revert(d)
+        });
+        revert_stack(self.float_vector_stack, |d| {
+            push_state.float_vector_stack.revert(d)
+        });
+        revert_stack(self.int_vector_stack, |d| {
+            push_state.int_vector_stack.revert(d)
+        });
+        revert_stack(self.input_stack, |d| push_state.input_stack.revert(d));
+        revert_stack(self.output_stack, |d| push_state.output_stack.revert(d));
+        revert_stack(self.graph_stack, |d| push_state.graph_stack.revert(d));
+        for (name, previous) in self.name_bindings.into_iter().rev() {
+            match previous {
+                Some(item) => push_state.name_bindings.insert(name, item),
+                None => push_state.name_bindings.remove(&name),
+            };
+        }
+        push_state.quote_name = self.quote_name;
+        push_state.send_name = self.send_name;
+        push_state.started = self.started;
+        push_state.steps = self.steps;
+        push_state.elapsed = self.elapsed;
+        push_state.rng.set_word_pos(self.rng_position);
+    }
+}
+
+/// Deltas of the most recent steps. The oldest delta is dropped when the
+/// capacity is reached.
+pub struct Journal {
+    deltas: VecDeque<StateDelta>,
+    // Maximum number of deltas
+    pub capacity: usize,
+}
+
+impl Journal {
+    pub fn new(capacity: usize) -> Self {
+        Self {
+            deltas: VecDeque::new(),
+            capacity,
+        }
+    }
+
+    /// Returns the number of steps that can be reverted.
+    pub fn len(&self) -> usize {
+        self.deltas.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.deltas.is_empty()
+    }
+
+    pub fn clear(&mut self) {
+        self.deltas.clear();
+    }
+
+    /// Adds the delta of the most recent step.
+    pub fn push(&mut self, delta: StateDelta) {
+        if self.capacity == 0 {
+            return;
+        }
+        if self.deltas.len() == self.capacity {
+            self.deltas.pop_front();
+        }
+        self.deltas.push_back(delta);
+    }
+
+    /// Removes and returns the delta of the most recent step.
+    pub fn pop(&mut self) -> Option<StateDelta> {
+        self.deltas.pop_back()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::push::instructions::InstructionSet;
+    use crate::push::interpreter::PushInterpreter;
+    use crate::push::parser::PushParser;
+
+    #[test]
+    fn journal_drops_oldest_delta() {
+        let mut push_state = PushState::new();
+        let mut journal = Journal::new(2);
+        for i in 0..3 {
+            let delta = StateDelta::begin(&mut push_state);
+            push_state.int_stack.push(i);
+            journal.push(delta.end(&mut push_state));
+        }
+        assert_eq!(journal.len(), 2);
+        journal.pop().unwrap().revert(&mut push_state);
+        journal.pop().unwrap().revert(&mut push_state);
+        assert!(journal.pop().is_none());
+        assert_eq!(push_state.int_stack.to_string(), "0");
+    }
+
+    #[test]
+    fn revert_restores_bindings_and_graphs() {
+        let mut instruction_set = InstructionSet::new();
+        instruction_set.load();
+        let mut push_state = PushState::with_seed(5);
+        push_state.graph_stack.push(Graph::new());
+        push_state.bind("X".to_string(), Item::int(1));
+        PushParser::parse_program(
+            &mut push_state,
+            &instruction_set,
+            "( 2 NAME.QUOTE X INTEGER.DEFINE 3 Y INTEGER.DEFINE 4 GRAPH.NODE*ADD INTEGER.RAND )",
+        );
+        let before = push_state.to_string();
+        let delta = StateDelta::begin(&mut push_state);
+        PushInterpreter::run(&mut push_state, &instruction_set);
+        let delta = delta.end(&mut push_state);
+        assert_eq!(push_state.name_bindings.len(), 2);
+        assert_eq!(push_state.graph_stack.get(0).unwrap().nodes.len(), 1);
+        let random_int = push_state.int_stack.copy(0);
+        let node_id = *push_state.graph_stack.get(0).unwrap().nodes.keys().next().unwrap();
+        delta.revert(&mut push_state);
+        assert_eq!(push_state.to_string(), before);
+        assert_eq!(push_state.name_bindings["X"].to_string(), "1");
+        assert_eq!(push_state.graph_stack.get(0).unwrap().nodes.len(), 0);
+        PushInterpreter::run(&mut push_state, &instruction_set);
+        assert_eq!(push_state.name_bindings["X"].to_string(), "2");
+        assert_eq!(push_state.name_bindings["Y"].to_string(), "3");
+        // Same random number as the random number generator has been restored
+        assert_eq!(push_state.int_stack.copy(0), random_int);
+        // The node id counter is not restored
+        let new_node_id = *push_state.graph_stack.get(0).unwrap().nodes.keys().next().unwrap();
+        assert!(new_node_id > node_id);
+    }
+}
@@ -0,0 +1,221 @@
+use crate::push::parser::{ParseError, ParseErrorKind};
+
+/// Token of a Push program with its position (both starting at 1).
+#[derive(Clone, Debug, PartialEq)]
+pub struct Token<'a> {
+    pub line: usize,
+    pub column: usize,
+    pub text: &'a str,
+}
+
+impl<'a> Token<'a> {
+    pub fn new(line: usize, column: usize, text: &'a str) -> Self {
+        Self { line, column, text }
+    }
+}
+
+pub struct Lexer {}
+
+impl Lexer {
+    /// Splits Push code into tokens. Parentheses are always separate tokens,
+    /// vector literals like INT[1, 2, 3] form a single token including the
+    /// whitespace between the brackets and all other tokens are separated by
+    /// whitespace or parentheses. Line comments start with ';' and block
+    /// comments are enclosed by '#|' and '|#'. Block comments can be nested.
+    /// Returns an error if a block comment is not terminated.
+    pub fn tokenize(code: &str) -> Result<Vec<Token<'_>>, ParseError> {
+        match Lexer::scan(code) {
+            (tokens, None) => Ok(tokens),
+            (_, Some(error)) => Err(error),
+        }
+    }
+
+    /// Same as tokenize but treats an unterminated block comment
+    /// as comment until the end of the code.
+    pub fn tokenize_lenient(code: &str) -> Vec<Token<'_>> {
+        Lexer::scan(code).0
+    }
+
+    fn push_token<'a>(
+        tokens: &mut Vec<Token<'a>>,
+        code: &'a str,
+        token_start: Option<(usize, usize, usize)>,
+        end: usize,
+    ) {
+        if let Some((start, line, column)) = token_start {
+            tokens.push(Token::new(line, column, &code[start..end]));
+        }
+    }
+
+    fn scan(code: &str) -> (Vec<Token<'_>>, Option<ParseError>) {
+        let mut tokens = vec![];
+        let mut chars = code.char_indices().peekable();
+        let mut line = 1;
+        let mut column = 1;
+        // Byte offset, line and column of the current token
+        let mut token_start: Option<(usize, usize, usize)> = None;
+        // Set while the current token is inside square brackets
+        let mut in_vector = false;
+        // Set while inside a line comment
+        let mut in_line_comment = false;
+        // Nesting depth and position of the outermost open block comment
+        let mut block_comments = 0;
+        let mut block_comment_start = (0, 0);
+        while let Some((byte_idx, c)) = chars.next() {
+            let next = chars.peek().map(|(_, next_c)| *next_c);
+            let next_byte_idx = chars.peek().map_or(code.len(), |(idx, _)| *idx);
+            if in_line_comment {
+                in_line_comment = c != '\n';
+            } else if block_comments > 0 {
+                if c == '|' && next == Some('#') {
+                    block_comments -= 1;
+                    chars.next();
+                    column += 1;
+                } else if c == '#' && next == Some('|') {
+                    block_comments += 1;
+                    chars.next();
+                    column += 1;
+                }
+            } else if in_vector && c != '(' && c != ')' {
+                if c == ']' {
+                    in_vector = false;
+                    Lexer::push_token(&mut tokens, code, token_start.take(), next_byte_idx);
+                }
+            } else if c == ';' || (c == '#' && next == Some('|')) {
+                Lexer::push_token(&mut tokens, code, token_start.take(), byte_idx);
+                if c == ';' {
+                    in_line_comment = true;
+                } else {
+                    block_comments = 1;
+                    block_comment_start = (line, column);
+                    chars.next();
+                    column += 1;
+                }
+            } else if c.is_whitespace() || c == '(' || c == ')' {
+                in_vector = false;
+                Lexer::push_token(&mut tokens, code, token_start.take(), byte_idx);
+                if c == '(' || c == ')' {
+                    tokens.push(Token::new(line, column, &code[byte_idx..next_byte_idx]));
+                }
+            } else {
+                if token_start.is_none() {
+                    token_start = Some((byte_idx, line, column));
+                }
+                if c == '[' {
+                    in_vector = true;
+                }
+            }
+            if c == '\n' {
+                line += 1;
+                column = 1;
+            } else {
+                column += 1;
+            }
+        }
+        Lexer::push_token(&mut tokens, code, token_start, code.len());
+        if block_comments > 0 {
+            let (line, column) = block_comment_start;
+            let error = ParseError::new(line, column, "#|", ParseErrorKind::UnterminatedComment);
+            return (tokens, Some(error));
+        }
+        (tokens, None)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn texts(code: &str) -> Vec<&str> {
+        Lexer::tokenize(code).unwrap().iter().map(|t| t.text).collect()
+    }
+
+    #[test]
+    fn tokenize_splits_on_whitespace() {
+        assert_eq!(texts(" ( 1  2\tINTEGER.+ )\n"), vec!["(", "1", "2", "INTEGER.+", ")"]);
+    }
+
+    #[test]
+    fn tokenize_parentheses_without_whitespace() {
+        assert_eq!(texts("(1 2 INTEGER.+)"), vec!["(", "1", "2", "INTEGER.+", ")"]);
+        assert_eq!(
+            texts("((BOOL[1,0])FLOAT[1.5])"),
+            vec!["(", "(", "BOOL[1,0]", ")", "FLOAT[1.5]", ")"]
+        );
+    }
+
+    #[test]
+    fn tokenize_vector_with_whitespace() {
+        assert_eq!(texts("( INT[1, 2,\n 3] 4 )"), vec!["(", "INT[1, 2,\n 3]", "4", ")"]);
+    }
+
+    #[test]
+    fn tokenize_unterminated_vector_stops_at_parenthesis() {
+        assert_eq!(texts("( INT[1, 2 ) 3"), vec!["(", "INT[1, 2 ", ")", "3"]);
+    }
+
+    #[test]
+    fn tokenize_records_positions() {
+        assert_eq!(
+            Lexer::tokenize("(1\n  äb)").unwrap(),
+            vec![
+                Token::new(1, 1, "("),
+                Token::new(1, 2, "1"),
+                Token::new(2, 3, "äb"),
+                Token::new(2, 5, ")"),
+            ]
+        );
+    }
+
+    #[test]
+    fn tokenize_skips_line_comments() {
+        assert_eq!(
+            texts("; Adds one\n( 1 INTEGER.+ ) ; to the top item\n;( 2 )"),
+            vec!["(", "1", "INTEGER.+", ")"]
+        );
+        assert_eq!(texts("( 1;comment\n2 )"), vec!["(", "1", "2", ")"]);
+    }
+
+    #[test]
+    fn tokenize_skips_block_comments() {
+        assert_eq!(
+            texts("#| Adds\n one |# ( 1 #|( 2 )|# INTEGER.+ )"),
+            vec!["(", "1", "INTEGER.+", ")"]
+        );
+        assert_eq!(texts("( 1#|comment|#2 )"), vec!["(", "1", "2", ")"]);
+    }
+
+    #[test]
+    fn tokenize_skips_nested_block_comments() {
+        assert_eq!(
+            texts("( 1 #| outer #| inner |# still outer |# 2 )"),
+            vec!["(", "1", "2", ")"]
+        );
+    }
+
+    #[test]
+    fn tokenize_keeps_position_after_comments() {
+        assert_eq!(
+            Lexer::tokenize("#| a |# 1 ; b\n #|\n|# 2").unwrap(),
+            vec![Token::new(1, 9, "1"), Token::new(3, 4, "2")]
+        );
+    }
+
+    #[test]
+    fn tokenize_reports_unterminated_block_comment() {
+        assert_eq!(
+            Lexer::tokenize("( 1 )\n  #| #| |# 2 )"),
+            Err(ParseError::new(
+                2,
+                3,
+                "#|",
+                ParseErrorKind::UnterminatedComment
+            ))
+        );
+        let tokens: Vec<&str> = Lexer::tokenize_lenient("( 1 #| 2 )")
+            .iter()
+            .map(|t| t.text)
+            .collect();
+        assert_eq!(tokens, vec!["(", "1"]);
+    }
+}
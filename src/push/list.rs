@@ -513,7 +513,7 @@ mod tests {
         let mut instruction_set = InstructionSet::new();
         instruction_set.load();
         assert_eq!(
-            PushInterpreter::run(&mut test_state, &mut instruction_set),
+            PushInterpreter::run(&mut test_state, &instruction_set).state,
             PushInterpreterState::NoErrors
         );
         assert_eq!(
@@ -546,7 +546,7 @@ mod tests {
         let mut instruction_set = InstructionSet::new();
         instruction_set.load();
         assert_eq!(
-            PushInterpreter::run(&mut test_state, &mut instruction_set),
+            PushInterpreter::run(&mut test_state, &instruction_set).state,
             PushInterpreterState::NoErrors
         );
         assert_eq!(
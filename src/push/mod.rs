@@ -1,7 +1,10 @@
+pub mod batch;
 pub mod boolean;
 pub mod buffer;
 pub mod code;
 pub mod configuration;
+pub mod debugger;
+pub mod evolution;
 pub mod execution;
 pub mod float;
 pub mod graph;
@@ -11,11 +14,20 @@ pub mod integer;
 pub mod interpreter;
 pub mod io;
 pub mod item;
+pub mod journal;
+pub mod lexer;
 pub mod list;
 pub mod name;
 pub mod parser;
+pub mod plushy;
+pub mod printer;
+pub mod profiler;
+pub mod program;
 pub mod random;
+pub mod selection;
+pub mod simplification;
 pub mod stack;
 pub mod state;
 pub mod topology;
+pub mod variation;
 pub mod vector;
@@ -91,14 +91,14 @@ pub fn name_quote(push_state: &mut PushState, _instruction_cache: &InstructionCa
 
 /// NAME.RAND: Pushes a newly generated random NAME.
 pub fn name_rand(push_state: &mut PushState, _instruction_cache: &InstructionCache) {
-    push_state.name_stack.push(CodeGenerator::new_random_name());
+    let rand_name = CodeGenerator::new_random_name(&mut push_state.rng);
+    push_state.name_stack.push(rand_name);
 }
 
 /// NAME.RANDBOUNDNAME: Pushes a randomly selected NAME that already has a definition.
 pub fn name_rand_bound(push_state: &mut PushState, _instruction_cache: &InstructionCache) {
-    push_state
-        .name_stack
-        .push(CodeGenerator::existing_random_name(push_state));
+    let rand_name = CodeGenerator::existing_random_name(push_state);
+    push_state.name_stack.push(rand_name);
 }
 
 /// NAME.ROT: Rotates the top three items on the NAME stack, pulling the third item out and pushing
@@ -216,7 +216,7 @@ mod tests {
         let mut test_state = PushState::new();
         test_state
             .name_bindings
-            .insert(CodeGenerator::new_random_name(), Item::int(1));
+            .insert(CodeGenerator::new_random_name(&mut test_state.rng), Item::int(1));
         name_rand_bound(&mut test_state, &icache());
         assert_eq!(test_state.name_stack.size(), 1);
     }
@@ -1,8 +1,14 @@
 use crate::push::instructions::InstructionSet;
-use crate::push::item::Item;
+use crate::push::graph::Graph;
+use crate::push::index::Index;
+use crate::push::item::{Item, PushType};
+use crate::push::lexer::{Lexer, Token};
 use crate::push::stack::PushStack;
 use crate::push::state::PushState;
 use crate::push::vector::{BoolVector, FloatVector, IntVector};
+use std::fmt;
+use std::path::PathBuf;
+use std::sync::Arc;
 
 pub struct PushParser {}
 
@@ -26,7 +32,7 @@ impl PushParser {
             match &mut bottom_item {
                 Item::List { items } => {
                     // If the bottm element is a List push to its stack
-                    return PushParser::rec_push(items, item, depth - 1);
+                    return PushParser::rec_push(Arc::make_mut(items), item, depth - 1);
                 }
                 _ => {
                     // Error: No more list found but depth > 0
@@ -57,153 +63,374 @@ impl PushParser {
         vector_type: &VectorType,
         vector_token: &str,
     ) {
+        if let Ok(item) = PushParser::vector_item(vector_type, vector_token) {
+            PushParser::rec_push(&mut push_state.exec_stack, item, depth);
+        }
+    }
+
+    /// Creates a vector item from the comma separated elements of a vector
+    /// token, i.e. the part between the square brackets.
+    pub fn vector_item(vector_type: &VectorType, vector_token: &str) -> Result<Item, ParseErrorKind> {
+        let elements: Vec<&str> = if vector_token.trim().is_empty() {
+            // Empty vector
+            vec![]
+        } else {
+            vector_token.split(',').map(|el| el.trim()).collect()
+        };
+        if elements.iter().any(|el| el.is_empty()) {
+            return Err(ParseErrorKind::EmptyToken);
+        }
         match vector_type {
             VectorType::Bool => {
                 let mut bv = vec![];
-                for el in vector_token.split(",") {
+                for el in elements {
                     if "1" == el || "true" == el {
                         bv.push(true);
                     } else if "0" == el || "false" == el {
                         bv.push(false);
                     } else {
-                        return;
+                        return Err(ParseErrorKind::BadVectorElement);
                     }
                 }
-                PushParser::rec_push(
-                    &mut push_state.exec_stack,
-                    Item::boolvec(BoolVector::new(bv)),
-                    depth,
-                );
+                Ok(Item::boolvec(BoolVector::new(bv)))
             }
             VectorType::Int => {
                 let mut iv = vec![];
-                for el in vector_token.split(",") {
-                    match el.to_string().parse::<i32>() {
+                for el in elements {
+                    match el.parse::<i32>() {
                         Ok(ival) => iv.push(ival),
-                        Err(_) => return,
+                        Err(_) => return Err(ParseErrorKind::BadVectorElement),
                     }
                 }
-                PushParser::rec_push(
-                    &mut push_state.exec_stack,
-                    Item::intvec(IntVector::new(iv)),
-                    depth,
-                );
+                Ok(Item::intvec(IntVector::new(iv)))
             }
             VectorType::Float => {
                 let mut fv = vec![];
-                for el in vector_token.split(",") {
-                    match el.to_string().parse::<f32>() {
+                for el in elements {
+                    match el.parse::<f32>() {
                         Ok(fval) => fv.push(fval),
-                        Err(_) => return,
+                        Err(_) => return Err(ParseErrorKind::BadVectorElement),
                     }
                 }
-                PushParser::rec_push(
-                    &mut push_state.exec_stack,
-                    Item::floatvec(FloatVector::new(fv)),
-                    depth,
-                );
+                Ok(Item::floatvec(FloatVector::new(fv)))
             }
         }
     }
 
-    /// Splits a string into tokens and front pushes it to the stack s.t. the
-    /// end of the string ends up at the top of the stack.
-    pub fn parse_program(push_state: &mut PushState, instruction_set: &InstructionSet, code: &str) {
-        let mut depth = 0;
-        for token in code.split_whitespace() {
-            if token.starts_with("INT[") {
-                PushParser::parse_vector(
-                    push_state,
-                    depth,
-                    &VectorType::Int,
-                    &token[4..token.len() - 1],
-                );
-                continue;
+    /// Creates an index item from a literal of the form current/destination,
+    /// i.e. the part between the square brackets of INDEX[...].
+    pub fn index_item(index_token: &str) -> Result<Item, ParseErrorKind> {
+        let parts: Vec<&str> = index_token.split('/').map(|el| el.trim()).collect();
+        if parts.len() != 2 || parts.iter().any(|el| el.is_empty()) {
+            return Err(ParseErrorKind::BadLiteral);
+        }
+        match (parts[0].parse::<usize>(), parts[1].parse::<usize>()) {
+            (Ok(current), Ok(destination)) => {
+                let mut index = Index::new(destination);
+                index.current = current;
+                Ok(Item::index(index))
             }
-            if token.starts_with("FLOAT[") {
-                PushParser::parse_vector(
-                    push_state,
-                    depth,
-                    &VectorType::Float,
-                    &token[6..token.len() - 1],
-                );
-                continue;
+            _ => Err(ParseErrorKind::BadLiteral),
+        }
+    }
+
+    /// Creates a graph item from a literal of the form nodes|edges where nodes
+    /// is a comma separated list of id:state and edges a comma separated list
+    /// of origin>destination:weight, i.e. the part between the square brackets
+    /// of GRAPH[...].
+    pub fn graph_item(graph_token: &str) -> Result<Item, ParseErrorKind> {
+        let mut graph = Graph::new();
+        let sections: Vec<&str> = graph_token.split('|').collect();
+        if sections.len() > 2 {
+            return Err(ParseErrorKind::BadLiteral);
+        }
+        let elements = |section: &str| -> Result<Vec<(String, String)>, ParseErrorKind> {
+            let mut elements = vec![];
+            if section.trim().is_empty() {
+                return Ok(elements);
             }
-            if token.starts_with("BOOL[") {
-                PushParser::parse_vector(
-                    push_state,
-                    depth,
-                    &VectorType::Bool,
-                    &token[5..token.len() - 1],
-                );
-                continue;
+            for el in section.split(',') {
+                let el = el.trim();
+                if el.is_empty() {
+                    return Err(ParseErrorKind::EmptyToken);
+                }
+                match el.split_once(':') {
+                    Some((key, val)) => elements.push((key.trim().to_string(), val.trim().to_string())),
+                    None => return Err(ParseErrorKind::BadLiteral),
+                }
+            }
+            Ok(elements)
+        };
+        for (id, state) in elements(sections[0])? {
+            match (id.parse::<usize>(), state.parse::<i32>()) {
+                (Ok(id), Ok(state)) => {
+                    if !graph.add_node_with_id(id, state) {
+                        return Err(ParseErrorKind::BadLiteral);
+                    }
+                }
+                _ => return Err(ParseErrorKind::BadLiteral),
+            }
+        }
+        if sections.len() == 2 {
+            for (connection, weight) in elements(sections[1])? {
+                let ids = match connection.split_once('>') {
+                    Some((origin, destination)) => {
+                        (origin.trim().parse::<usize>(), destination.trim().parse::<usize>())
+                    }
+                    None => return Err(ParseErrorKind::BadLiteral),
+                };
+                match (ids, weight.parse::<f32>()) {
+                    ((Ok(origin), Ok(destination)), Ok(weight))
+                        if graph.nodes.contains_key(&origin)
+                            && graph.nodes.contains_key(&destination) =>
+                    {
+                        graph.add_edge(origin, destination, weight)
+                    }
+                    _ => return Err(ParseErrorKind::BadLiteral),
+                }
             }
+        }
+        Ok(Item::Literal {
+            push_type: PushType::Graph { val: graph },
+        })
+    }
+
+    /// Converts a single token that is not a parenthesis to an item.
+    fn token_item(instruction_set: &InstructionSet, token: &str) -> Result<Item, ParseErrorKind> {
+        for prefix in ["INDEX[", "GRAPH["].iter() {
+            if token.starts_with(prefix) {
+                if token.len() <= prefix.len() || !token.ends_with(']') {
+                    return Err(ParseErrorKind::BadLiteral);
+                }
+                let content = &token[prefix.len()..token.len() - 1];
+                if *prefix == "INDEX[" {
+                    return PushParser::index_item(content);
+                }
+                return PushParser::graph_item(content);
+            }
+        }
+        let vector_prefixes = [
+            ("INT[", VectorType::Int),
+            ("FLOAT[", VectorType::Float),
+            ("BOOL[", VectorType::Bool),
+        ];
+        for (prefix, vector_type) in vector_prefixes.iter() {
+            if token.starts_with(prefix) {
+                if token.len() <= prefix.len() || !token.ends_with(']') {
+                    return Err(ParseErrorKind::BadVectorElement);
+                }
+                return PushParser::vector_item(vector_type, &token[prefix.len()..token.len() - 1]);
+            }
+        }
+        // Check for instruction
+        if instruction_set.is_instruction(token) {
+            return Ok(Item::instruction(token.to_string()));
+        }
+        // Check for Literal
+        if let Ok(ival) = token.parse::<i32>() {
+            return Ok(Item::int(ival));
+        }
+        if let Ok(fval) = token.parse::<f32>() {
+            return Ok(Item::float(fval));
+        }
+        match token {
+            "TRUE" => Ok(Item::bool(true)),
+            "FALSE" => Ok(Item::bool(false)),
+            &_ => Ok(Item::name(token.to_string())),
+        }
+    }
+
+    /// Parses the code into the stack. Invalid tokens and superfluous closing
+    /// parentheses are skipped unless strict is set, in which case the first
+    /// error is returned.
+    fn parse_into(
+        stack: &mut PushStack<Item>,
+        instruction_set: &InstructionSet,
+        code: &str,
+        strict: bool,
+    ) -> Result<(), ParseError> {
+        let tokens = if strict {
+            Lexer::tokenize(code)?
+        } else {
+            Lexer::tokenize_lenient(code)
+        };
+        PushParser::parse_tokens_into(stack, instruction_set, tokens, strict)
+    }
+
+    /// Same as parse_into for code that has already been split into tokens.
+    fn parse_tokens_into(
+        stack: &mut PushStack<Item>,
+        instruction_set: &InstructionSet,
+        tokens: Vec<Token>,
+        strict: bool,
+    ) -> Result<(), ParseError> {
+        // Positions of the open (sub) lists
+        let mut open_lists: Vec<(usize, usize)> = vec![];
+        for Token { line, column, text: token } in tokens {
             if "(" == token {
                 PushParser::rec_push(
-                    &mut push_state.exec_stack,
+                    stack,
                     Item::List {
-                        items: PushStack::new(),
+                        items: Arc::new(PushStack::new()),
                     },
-                    depth,
+                    open_lists.len(),
                 );
                 // Start of (sub) list
-                depth += 1;
+                open_lists.push((line, column));
                 continue;
             }
             if ")" == token {
                 // End of (sub) list
-                depth -= 1;
-                continue;
-            }
-
-            // Check for instruction
-            if instruction_set.is_instruction(token) {
-                PushParser::rec_push(
-                    &mut push_state.exec_stack,
-                    Item::instruction(token.to_string()),
-                    depth,
-                );
+                if open_lists.pop().is_none() && strict {
+                    return Err(ParseError::new(
+                        line,
+                        column,
+                        token,
+                        ParseErrorKind::UnbalancedParentheses,
+                    ));
+                }
                 continue;
             }
-            // Check for Literal
-            match token.to_string().parse::<i32>() {
-                Ok(ival) => {
-                    PushParser::rec_push(&mut push_state.exec_stack, Item::int(ival), depth);
-                    continue;
+            match PushParser::token_item(instruction_set, token) {
+                Ok(item) => {
+                    PushParser::rec_push(stack, item, open_lists.len());
                 }
-                Err(_) => (),
-            }
-            match token.to_string().parse::<f32>() {
-                Ok(fval) => {
-                    PushParser::rec_push(&mut push_state.exec_stack, Item::float(fval), depth);
-                    continue;
+                Err(kind) => {
+                    if strict {
+                        return Err(ParseError::new(line, column, token, kind));
+                    }
                 }
-                Err(_) => (),
             }
-
-            match token {
-                "TRUE" => {
-                    PushParser::rec_push(&mut push_state.exec_stack, Item::bool(true), depth);
-                    continue;
-                }
-                "FALSE" => {
-                    PushParser::rec_push(&mut push_state.exec_stack, Item::bool(false), depth);
-                    continue;
-                }
-                &_ => {
-                    PushParser::rec_push(
-                        &mut push_state.exec_stack,
-                        Item::name(token.to_string()),
-                        depth,
-                    );
-                }
+        }
+        if let Some((line, column)) = open_lists.pop() {
+            if strict {
+                return Err(ParseError::new(
+                    line,
+                    column,
+                    "(",
+                    ParseErrorKind::UnbalancedParentheses,
+                ));
             }
         }
+        Ok(())
+    }
+
+    /// Splits a string into tokens (see Lexer) and front pushes it to the stack s.t. the
+    /// end of the string ends up at the top of the stack. Malformed tokens are
+    /// ignored, see try_parse_program for a parser that reports them.
+    pub fn parse_program(push_state: &mut PushState, instruction_set: &InstructionSet, code: &str) {
+        let _ = PushParser::parse_into(&mut push_state.exec_stack, instruction_set, code, false);
+    }
+
+    /// Same as parse_program but returns the first malformed token as error.
+    /// The state is not modified in this case.
+    pub fn try_parse_program(
+        push_state: &mut PushState,
+        instruction_set: &InstructionSet,
+        code: &str,
+    ) -> Result<(), ParseError> {
+        let mut stack = PushStack::new();
+        PushParser::parse_into(&mut stack, instruction_set, code, true)?;
+        while let Some(item) = stack.pop() {
+            push_state.exec_stack.push_front(item);
+        }
+        Ok(())
+    }
+
+    /// Parses the code into a program item without modifying any state. A single
+    /// top-level item, e.g. a parenthesized list, is returned as it is. Several
+    /// top-level items are wrapped in a list.
+    pub fn parse(instruction_set: &InstructionSet, code: &str) -> Result<Item, ParseError> {
+        PushParser::parse_tokens(instruction_set, Lexer::tokenize(code)?)
+    }
+
+    /// Same as parse for code that has already been split into tokens.
+    pub fn parse_tokens(
+        instruction_set: &InstructionSet,
+        tokens: Vec<Token>,
+    ) -> Result<Item, ParseError> {
+        let mut stack = PushStack::new();
+        PushParser::parse_tokens_into(&mut stack, instruction_set, tokens, true)?;
+        if stack.size() == 1 {
+            Ok(stack.pop().unwrap())
+        } else {
+            Ok(Item::List {
+                items: Arc::new(stack),
+            })
+        }
+    }
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub enum ParseErrorKind {
+    // A closing parenthesis without opening parenthesis or vice versa
+    UnbalancedParentheses,
+    // An element of a vector literal that cannot be converted
+    BadVectorElement,
+    // An index or graph literal that cannot be converted
+    BadLiteral,
+    // An empty element of a vector literal
+    EmptyToken,
+    // A block comment without end
+    UnterminatedComment,
+    // A define directive without name or definition
+    BadDefinition,
+    // An include directive with a missing, unreadable or cyclic path
+    BadInclude,
+}
+
+/// Error of the parser with the position (starting at 1) and the
+/// content of the offending token.
+#[derive(Clone, Debug, PartialEq)]
+pub struct ParseError {
+    pub line: usize,
+    pub column: usize,
+    pub token: String,
+    pub kind: ParseErrorKind,
+    // File that contains the token if the code has been loaded from a file
+    pub path: Option<PathBuf>,
+}
+
+impl ParseError {
+    pub fn new(line: usize, column: usize, token: &str, kind: ParseErrorKind) -> Self {
+        Self {
+            line,
+            column,
+            token: token.to_string(),
+            kind,
+            path: None,
+        }
     }
 }
+
+impl fmt::Display for ParseError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let description = match self.kind {
+            ParseErrorKind::UnbalancedParentheses => "unbalanced parentheses",
+            ParseErrorKind::BadVectorElement => "bad vector element",
+            ParseErrorKind::BadLiteral => "bad literal",
+            ParseErrorKind::EmptyToken => "empty token",
+            ParseErrorKind::UnterminatedComment => "unterminated comment",
+            ParseErrorKind::BadDefinition => "bad definition",
+            ParseErrorKind::BadInclude => "bad include",
+        };
+        if let Some(path) = &self.path {
+            write!(f, "{}: ", path.display())?;
+        }
+        write!(
+            f,
+            "{} at line {}, column {}: '{}'",
+            description, self.line, self.column, self.token
+        )
+    }
+}
+
+impl std::error::Error for ParseError {}
+
 #[cfg(test)]
 mod tests {
     use super::*;
+    use crate::push::interpreter::PushInterpreter;
 
     #[test]
     pub fn parse_simple_program() {
@@ -267,4 +494,178 @@ mod tests {
             "( [1,2,3] )"
         );
     }
+
+    #[test]
+    pub fn parse_program_ignores_superfluous_closing_parentheses() {
+        let input = "( 1 ) ) 2";
+        let mut push_state = PushState::new();
+        let mut instruction_set = InstructionSet::new();
+        instruction_set.load();
+        PushParser::parse_program(&mut push_state, &instruction_set, &input);
+        assert_eq!(push_state.exec_stack.to_string(), "( 1 ) 2");
+    }
+
+    #[test]
+    pub fn try_parse_program_returns_program() {
+        let input = "( 2 3 INTEGER.* ) INT[1,2]";
+        let mut push_state = PushState::new();
+        let mut instruction_set = InstructionSet::new();
+        instruction_set.load();
+        assert_eq!(
+            PushParser::try_parse_program(&mut push_state, &instruction_set, &input),
+            Ok(())
+        );
+        assert_eq!(push_state.exec_stack.to_string(), "( 2 3 INTEGER.* ) [1,2]");
+    }
+
+    #[test]
+    pub fn try_parse_program_reports_bad_vector_element() {
+        let input = "( 1 2\n  INT[2,345,-5.0] )";
+        let mut push_state = PushState::new();
+        let mut instruction_set = InstructionSet::new();
+        instruction_set.load();
+        assert_eq!(
+            PushParser::try_parse_program(&mut push_state, &instruction_set, &input),
+            Err(ParseError::new(
+                2,
+                3,
+                "INT[2,345,-5.0]",
+                ParseErrorKind::BadVectorElement
+            ))
+        );
+        assert_eq!(push_state.exec_stack.size(), 0);
+    }
+
+    #[test]
+    pub fn try_parse_program_reports_empty_token() {
+        let input = "( BOOL[1,,0] )";
+        let mut push_state = PushState::new();
+        let instruction_set = InstructionSet::new();
+        let error = PushParser::try_parse_program(&mut push_state, &instruction_set, &input)
+            .unwrap_err();
+        assert_eq!(error.kind, ParseErrorKind::EmptyToken);
+        assert_eq!(error.column, 3);
+    }
+
+    #[test]
+    pub fn try_parse_program_reports_unbalanced_parentheses() {
+        let mut push_state = PushState::new();
+        let instruction_set = InstructionSet::new();
+        assert_eq!(
+            PushParser::try_parse_program(&mut push_state, &instruction_set, "( 1 ) )"),
+            Err(ParseError::new(
+                1,
+                7,
+                ")",
+                ParseErrorKind::UnbalancedParentheses
+            ))
+        );
+        let error = PushParser::try_parse_program(&mut push_state, &instruction_set, "( ( 1 )")
+            .unwrap_err();
+        assert_eq!(error.kind, ParseErrorKind::UnbalancedParentheses);
+        assert_eq!((error.line, error.column), (1, 1));
+        assert_eq!(
+            error.to_string(),
+            "unbalanced parentheses at line 1, column 1: '('"
+        );
+    }
+
+    #[test]
+    pub fn parse_program_without_whitespace_around_parentheses() {
+        let input = "(1 2 INTEGER.+(BOOL[1,0])INT[1, 2, 3])";
+        let mut push_state = PushState::new();
+        let mut instruction_set = InstructionSet::new();
+        instruction_set.load();
+        assert_eq!(
+            PushParser::try_parse_program(&mut push_state, &instruction_set, &input),
+            Ok(())
+        );
+        assert_eq!(
+            push_state.exec_stack.to_string(),
+            "( 1 2 INTEGER.+ ( [TRUE,FALSE] ) [1,2,3] )"
+        );
+    }
+
+    #[test]
+    pub fn parse_program_with_comments() {
+        let input = "; Doubles the top INTEGER item
+                     ( INTEGER.DUP #| keep the original |# INTEGER.+ ) ; done";
+        let mut push_state = PushState::new();
+        let mut instruction_set = InstructionSet::new();
+        instruction_set.load();
+        assert_eq!(
+            PushParser::try_parse_program(&mut push_state, &instruction_set, &input),
+            Ok(())
+        );
+        assert_eq!(push_state.exec_stack.to_string(), "( INTEGER.DUP INTEGER.+ )");
+    }
+
+    #[test]
+    pub fn parse_returns_program_item() {
+        let input = "( 2 3 INTEGER.* ( TRUE ) )";
+        let mut instruction_set = InstructionSet::new();
+        instruction_set.load();
+        let program = PushParser::parse(&instruction_set, &input).unwrap();
+        assert_eq!(program.to_string(), "( 2 3 INTEGER.* ( TRUE ) )");
+        let mut push_state = PushState::new();
+        PushParser::parse_program(&mut push_state, &instruction_set, &input);
+        assert!(Item::equals(&program, push_state.exec_stack.get(0).unwrap()));
+    }
+
+    #[test]
+    pub fn parse_wraps_several_top_level_items() {
+        let mut instruction_set = InstructionSet::new();
+        instruction_set.load();
+        let program = PushParser::parse(&instruction_set, "2 3 INTEGER.*").unwrap();
+        assert_eq!(program.to_string(), "( 2 3 INTEGER.* )");
+        assert_eq!(PushParser::parse(&instruction_set, "").unwrap().to_string(), "(  )");
+        assert_eq!(
+            PushParser::parse(&instruction_set, "( 2").unwrap_err().kind,
+            ParseErrorKind::UnbalancedParentheses
+        );
+        let mut push_state = PushState::new();
+        push_state.exec_stack.push(program);
+        PushInterpreter::run(&mut push_state, &instruction_set);
+        assert_eq!(push_state.int_stack.to_string(), "6");
+    }
+
+    #[test]
+    pub fn parse_index_and_graph_literals() {
+        let mut instruction_set = InstructionSet::new();
+        instruction_set.load();
+        let program = PushParser::parse(
+            &instruction_set,
+            "( INDEX[1/4] GRAPH[1001:5, 1002:-1 | 1001>1002:0.5, 1002>1002:1.0] GRAPH[] )",
+        )
+        .unwrap();
+        let mut push_state = PushState::new();
+        push_state.exec_stack.push(program);
+        PushInterpreter::run(&mut push_state, &instruction_set);
+        assert_eq!(push_state.index_stack.to_string(), "1/4");
+        assert_eq!(push_state.graph_stack.size(), 2);
+        let graph = push_state.graph_stack.get(1).unwrap();
+        assert_eq!(graph.get_state(&1001), Some(5));
+        assert_eq!(graph.get_state(&1002), Some(-1));
+        assert_eq!(graph.get_weight(&1001, &1002), Some(0.5));
+        assert_eq!(graph.get_weight(&1002, &1002), Some(1.0));
+    }
+
+    #[test]
+    pub fn parse_reports_bad_index_and_graph_literals() {
+        let instruction_set = InstructionSet::new();
+        for input in vec![
+            "INDEX[1]",
+            "INDEX[a/2]",
+            "GRAPH[1:2|1>3:1.0]",
+            "GRAPH[1:x]",
+            "GRAPH[1:2|1:2|]",
+            "GRAPH[18446744073709551615:1]",
+            "INDEX[",
+        ] {
+            assert_eq!(
+                PushParser::parse(&instruction_set, input).unwrap_err().kind,
+                ParseErrorKind::BadLiteral
+            );
+        }
+    }
 }
@@ -0,0 +1,316 @@
+use crate::push::instructions::{InstructionCache, InstructionSet};
+use crate::push::item::Item;
+use crate::push::lexer::Lexer;
+use crate::push::parser::{ParseError, ParseErrorKind, PushParser};
+use crate::push::printer::PushPrinter;
+use crate::push::random::CodeGenerator;
+use crate::push::state::PushState;
+use rand::Rng;
+use std::fmt;
+
+/// Element of a linear genome: a single instruction or literal, or a
+/// marker that closes the most recently opened code block.
+#[derive(Clone, Debug)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum Gene {
+    Atom { item: Item },
+    Close,
+}
+
+impl fmt::Display for Gene {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Gene::Atom { item } => write!(f, "{}", PushPrinter::print(item)),
+            Gene::Close => write!(f, "CLOSE"),
+        }
+    }
+}
+
+/// Linear genome (Plushy) that is translated into a nested program. An
+/// instruction that takes n code blocks from the EXEC stack opens n blocks,
+/// e.g. EXEC.IF opens two. Each CLOSE ends the innermost open block and opens
+/// the next block of the same instruction if there is one left.
+#[derive(Clone, Debug)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct Plushy {
+    pub genes: Vec<Gene>,
+}
+
+impl Plushy {
+    pub fn new(genes: Vec<Gene>) -> Self {
+        Self { genes }
+    }
+
+    /// Parses genes with the same tokens, literals and comments as programs.
+    /// CLOSE is the close marker, all other tokens are parsed as single items.
+    /// Parentheses are reported as unbalanced since blocks are only closed
+    /// by CLOSE.
+    pub fn parse(instruction_set: &InstructionSet, code: &str) -> Result<Plushy, ParseError> {
+        let mut genes = vec![];
+        for token in Lexer::tokenize(code)? {
+            match token.text {
+                "CLOSE" => genes.push(Gene::Close),
+                "(" | ")" => {
+                    return Err(ParseError::new(
+                        token.line,
+                        token.column,
+                        token.text,
+                        ParseErrorKind::UnbalancedParentheses,
+                    ))
+                }
+                _ => genes.push(Gene::Atom {
+                    item: PushParser::parse_tokens(instruction_set, vec![token])?,
+                }),
+            }
+        }
+        Ok(Plushy::new(genes))
+    }
+
+    /// Returns a random genome with the given number of genes.
+    pub fn random(
+        push_state: &mut PushState,
+        instructions: &InstructionCache,
+        length: usize,
+        close_rate: f32,
+    ) -> Plushy {
+        Plushy::new(
+            (0..length)
+                .map(|_| Plushy::random_gene(push_state, instructions, close_rate))
+                .collect(),
+        )
+    }
+
+    /// Returns CLOSE with probability close_rate or a random atom otherwise.
+    pub fn random_gene(
+        push_state: &mut PushState,
+        instructions: &InstructionCache,
+        close_rate: f32,
+    ) -> Gene {
+        if push_state.rng.gen::<f32>() < close_rate {
+            Gene::Close
+        } else {
+            Gene::Atom {
+                item: CodeGenerator::random_code_with_size(push_state, instructions, 1),
+            }
+        }
+    }
+
+    /// Translates the genome into a program list. CLOSE markers without an
+    /// open block are ignored. Blocks that are still open at the end of the
+    /// genome are closed, remaining blocks of an instruction become empty
+    /// lists.
+    pub fn translate(&self, instruction_set: &InstructionSet) -> Item {
+        // Items of the open blocks and the number of blocks that the
+        // instruction still opens after the block is closed
+        let mut blocks: Vec<(Vec<Item>, usize)> = vec![(vec![], 0)];
+        for gene in &self.genes {
+            match gene {
+                Gene::Atom { item } => {
+                    let opens = match item {
+                        Item::InstructionMeta { id } => instruction_set.blocks(*id),
+                        _ => 0,
+                    };
+                    blocks.last_mut().unwrap().0.push(item.clone());
+                    if opens > 0 {
+                        blocks.push((vec![], opens - 1));
+                    }
+                }
+                Gene::Close => {
+                    if blocks.len() > 1 {
+                        Plushy::close_block(&mut blocks);
+                    }
+                }
+            }
+        }
+        while blocks.len() > 1 {
+            Plushy::close_block(&mut blocks);
+        }
+        Plushy::block(blocks.pop().unwrap().0)
+    }
+
+    fn close_block(blocks: &mut Vec<(Vec<Item>, usize)>) {
+        let (items, remaining) = blocks.pop().unwrap();
+        blocks.last_mut().unwrap().0.push(Plushy::block(items));
+        if remaining > 0 {
+            blocks.push((vec![], remaining - 1));
+        }
+    }
+
+    /// Creates a list whose first item is executed first.
+    fn block(mut items: Vec<Item>) -> Item {
+        items.reverse();
+        Item::list(items)
+    }
+
+    /// Uniform mutation by addition and deletion (UMAD). A random gene is
+    /// added before each gene with probability addition_rate, then each gene
+    /// is deleted with probability deletion_rate. The genome size does not
+    /// change on average for deletion_rate = addition_rate / (1 + addition_rate).
+    pub fn umad(
+        &self,
+        push_state: &mut PushState,
+        instructions: &InstructionCache,
+        close_rate: f32,
+        addition_rate: f32,
+        deletion_rate: f32,
+    ) -> Plushy {
+        let mut added = Vec::with_capacity(self.genes.len());
+        for gene in &self.genes {
+            if push_state.rng.gen::<f32>() < addition_rate {
+                added.push(Plushy::random_gene(push_state, instructions, close_rate));
+            }
+            added.push(gene.clone());
+        }
+        Plushy::new(
+            added
+                .into_iter()
+                .filter(|_| push_state.rng.gen::<f32>() >= deletion_rate)
+                .collect(),
+        )
+    }
+}
+
+impl fmt::Display for Plushy {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let genes: Vec<String> = self.genes.iter().map(|g| g.to_string()).collect();
+        write!(f, "{}", genes.join(" "))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::push::instructions::InstructionId;
+    use crate::push::interpreter::PushInterpreter;
+    use crate::push::vector::IntVector;
+
+    fn translate(code: &str) -> String {
+        let mut instruction_set = InstructionSet::new();
+        instruction_set.load();
+        Plushy::parse(&instruction_set, code)
+            .unwrap()
+            .translate(&instruction_set)
+            .to_string()
+    }
+
+    fn same_genes(first: &Plushy, second: &Plushy) -> bool {
+        first.genes.len() == second.genes.len()
+            && first
+                .genes
+                .iter()
+                .zip(&second.genes)
+                .all(|genes| match genes {
+                    (Gene::Atom { item }, Gene::Atom { item: other }) => Item::equals(item, other),
+                    (Gene::Close, Gene::Close) => true,
+                    _ => false,
+                })
+    }
+
+    #[test]
+    fn instructions_have_block_metadata() {
+        let mut instruction_set = InstructionSet::new();
+        instruction_set.load();
+        let blocks = |name: &str| instruction_set.blocks(InstructionId::intern(name));
+        assert_eq!(blocks("EXEC.IF"), 2);
+        assert_eq!(blocks("EXEC.LOOP"), 1);
+        assert_eq!(blocks("EXEC.S"), 3);
+        assert_eq!(blocks("CODE.QUOTE"), 1);
+        assert_eq!(blocks("CODE.LOOP"), 0);
+        assert_eq!(blocks("INTEGER.+"), 0);
+        assert_eq!(blocks("TEST.UNKNOWN"), 0);
+    }
+
+    #[test]
+    fn translate_opens_blocks_for_instructions() {
+        assert_eq!(
+            translate("1 EXEC.IF 2 CLOSE 3 CLOSE 4"),
+            "( 1 EXEC.IF ( 2 ) ( 3 ) 4 )"
+        );
+        assert_eq!(
+            translate("EXEC.DUP EXEC.DUP INTEGER.+ CLOSE INTEGER.* CLOSE TRUE"),
+            "( EXEC.DUP ( EXEC.DUP ( INTEGER.+ ) INTEGER.* ) TRUE )"
+        );
+    }
+
+    #[test]
+    fn translated_program_runs() {
+        let mut instruction_set = InstructionSet::new();
+        instruction_set.load();
+        let program = Plushy::parse(&instruction_set, "FALSE EXEC.IF 1 CLOSE 2 3 CLOSE 4")
+            .unwrap()
+            .translate(&instruction_set);
+        let mut push_state = PushState::new();
+        push_state.exec_stack.push(program);
+        PushInterpreter::run(&mut push_state, &instruction_set);
+        assert_eq!(push_state.int_stack.to_string(), "4 3 2");
+    }
+
+    #[test]
+    fn translate_ignores_unmatched_close_and_closes_open_blocks() {
+        assert_eq!(translate("CLOSE 1 CLOSE 2"), "( 1 2 )");
+        let mut instruction_set = InstructionSet::new();
+        instruction_set.load();
+        let program = Plushy::parse(&instruction_set, "EXEC.IF 1")
+            .unwrap()
+            .translate(&instruction_set);
+        assert_eq!(Item::size(&program), 5);
+        assert_eq!(Item::traverse(&program, 2).unwrap().to_string(), "( 1 )");
+        assert!(Item::equals(
+            &Item::traverse(&program, 4).unwrap(),
+            &Item::empty_list()
+        ));
+    }
+
+    #[test]
+    fn parse_accepts_program_literals_and_comments() {
+        let mut instruction_set = InstructionSet::new();
+        instruction_set.load();
+        let plushy = Plushy::parse(
+            &instruction_set,
+            "INT[1, 2] ; comment\n#| block |# EXEC.IF 3 CLOSE",
+        )
+        .unwrap();
+        assert_eq!(plushy.genes.len(), 4);
+        match &plushy.genes[0] {
+            Gene::Atom { item } => assert!(Item::equals(
+                item,
+                &Item::intvec(IntVector::new(vec![1, 2]))
+            )),
+            Gene::Close => panic!("expected an atom"),
+        }
+        let error = Plushy::parse(&instruction_set, "1 CLOSE\n  ( 2").unwrap_err();
+        assert_eq!(error.line, 2);
+        assert_eq!(error.column, 3);
+        assert_eq!(error.kind, ParseErrorKind::UnbalancedParentheses);
+    }
+
+    #[test]
+    fn printed_genome_parses_to_same_genes() {
+        let mut instruction_set = InstructionSet::new();
+        instruction_set.load();
+        let plushy = Plushy::parse(
+            &instruction_set,
+            "0.123456789 FLOAT[1.5, 0.1] INT[1, 2] EXEC.IF CLOSE",
+        )
+        .unwrap();
+        let reparsed = Plushy::parse(&instruction_set, &plushy.to_string()).unwrap();
+        assert_eq!(reparsed.to_string(), plushy.to_string());
+        assert!(same_genes(&reparsed, &plushy));
+    }
+
+    #[test]
+    fn umad_adds_and_deletes_genes() {
+        let mut instruction_set = InstructionSet::new();
+        instruction_set.load();
+        let instructions = instruction_set.cache();
+        let mut push_state = PushState::with_seed(1);
+        let plushy = Plushy::random(&mut push_state, &instructions, 50, 0.1);
+        assert_eq!(plushy.genes.len(), 50);
+        let unchanged = plushy.umad(&mut push_state, &instructions, 0.1, 0.0, 0.0);
+        assert!(same_genes(&unchanged, &plushy));
+        let grown = plushy.umad(&mut push_state, &instructions, 0.1, 1.0, 0.0);
+        assert_eq!(grown.genes.len(), 100);
+        let deleted = plushy.umad(&mut push_state, &instructions, 0.1, 0.0, 1.0);
+        assert!(deleted.genes.is_empty());
+    }
+}
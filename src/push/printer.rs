@@ -0,0 +1,193 @@
+use crate::push::item::{Item, PushType};
+
+pub struct PushPrinter {}
+
+impl PushPrinter {
+    /// Prints the item in the canonical program syntax. In contrast to Display,
+    /// floats are printed without rounding and vectors with their type prefix
+    /// so that PushParser reads the output back into an identical item, i.e.
+    /// Item::equals returns true. Instructions must be part of the instruction
+    /// set of the parser and names must not be valid literals or instructions.
+    pub fn print(item: &Item) -> String {
+        let mut code = String::new();
+        PushPrinter::print_into(&mut code, item);
+        code
+    }
+
+    fn print_into(code: &mut String, item: &Item) {
+        match item {
+            Item::List { items } => {
+                code.push('(');
+                // Top of the stack is the first element of the list
+                for i in 0..items.size() {
+                    code.push(' ');
+                    PushPrinter::print_into(code, items.get(i).unwrap());
+                }
+                code.push_str(" )");
+            }
+            Item::InstructionMeta { id } => code.push_str(id.name()),
+            Item::Literal { push_type } => code.push_str(&PushPrinter::print_literal(push_type)),
+            Item::Identifier { name } => code.push_str(name),
+        }
+    }
+
+    /// Prints a literal in the canonical program syntax.
+    pub fn print_literal(push_type: &PushType) -> String {
+        match push_type {
+            PushType::Bool { val } => val.to_string().to_uppercase(),
+            PushType::Int { val } => val.to_string(),
+            PushType::Float { val } => PushPrinter::print_float(*val),
+            PushType::BoolVector { val } => format!(
+                "BOOL[{}]",
+                val.values
+                    .iter()
+                    .map(|v| if *v { "1" } else { "0" })
+                    .collect::<Vec<&str>>()
+                    .join(",")
+            ),
+            PushType::IntVector { val } => format!(
+                "INT[{}]",
+                val.values
+                    .iter()
+                    .map(|v| v.to_string())
+                    .collect::<Vec<String>>()
+                    .join(",")
+            ),
+            PushType::FloatVector { val } => format!(
+                "FLOAT[{}]",
+                val.values
+                    .iter()
+                    .map(|v| PushPrinter::print_float(*v))
+                    .collect::<Vec<String>>()
+                    .join(",")
+            ),
+            PushType::Index { val } => format!("INDEX[{}/{}]", val.current, val.destination),
+            PushType::Graph { val } => {
+                let mut node_ids: Vec<&usize> = val.nodes.keys().collect();
+                node_ids.sort();
+                let nodes: Vec<String> = node_ids
+                    .iter()
+                    .map(|id| format!("{}:{}", id, val.nodes[id].get_state()))
+                    .collect();
+                let mut destination_ids: Vec<&usize> = val.edges.keys().collect();
+                destination_ids.sort();
+                let mut edges: Vec<String> = vec![];
+                for destination_id in destination_ids {
+                    for edge in val.edges[destination_id].iter() {
+                        edges.push(format!(
+                            "{}>{}:{}",
+                            edge.get_origin_id(),
+                            destination_id,
+                            PushPrinter::print_float(edge.get_weight())
+                        ));
+                    }
+                }
+                format!("GRAPH[{}|{}]", nodes.join(","), edges.join(","))
+            }
+        }
+    }
+
+    /// Prints the shortest representation that parses back to the same value.
+    /// It always contains a decimal point or exponent so that it is not read
+    /// as integer.
+    pub fn print_float(val: f32) -> String {
+        format!("{:?}", val)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::push::graph::Graph;
+    use crate::push::index::Index;
+    use crate::push::instructions::InstructionSet;
+    use crate::push::parser::PushParser;
+    use crate::push::random::CodeGenerator;
+    use crate::push::state::PushState;
+    use crate::push::vector::{BoolVector, FloatVector, IntVector};
+
+    fn assert_round_trip(item: &Item) {
+        let mut instruction_set = InstructionSet::new();
+        instruction_set.load();
+        let code = PushPrinter::print(item);
+        let parsed = PushParser::parse(&instruction_set, &code).unwrap();
+        assert!(Item::equals(item, &parsed), "{} != {}", code, PushPrinter::print(&parsed));
+    }
+
+    #[test]
+    fn print_nested_lists() {
+        let item = Item::list(vec![
+            Item::list(vec![]),
+            Item::instruction("INTEGER.+".to_string()),
+            Item::list(vec![Item::bool(false), Item::int(-3)]),
+        ]);
+        assert_eq!(PushPrinter::print(&item), "( ( -3 FALSE ) INTEGER.+ ( ) )");
+        assert_round_trip(&item);
+    }
+
+    #[test]
+    fn print_floats_without_loss() {
+        for val in vec![0.1, -2.0, 1.0e-7, 3.4028235e38, 0.123456789, 16.0] {
+            let item = Item::list(vec![Item::float(val)]);
+            assert_round_trip(&item);
+        }
+        assert_eq!(PushPrinter::print(&Item::float(2.0)), "2.0");
+        assert_eq!(PushPrinter::print(&Item::float(0.1234)), "0.1234");
+        assert_eq!(PushPrinter::print(&Item::float(f32::NAN)), "NaN");
+        assert_round_trip(&Item::list(vec![
+            Item::float(f32::NAN),
+            Item::floatvec(FloatVector::new(vec![1.0, f32::NAN])),
+        ]));
+        assert!(!Item::equals(&Item::float(f32::NAN), &Item::float(1.0)));
+    }
+
+    #[test]
+    fn print_vectors_and_names() {
+        let item = Item::list(vec![
+            Item::boolvec(BoolVector::new(vec![true, false])),
+            Item::intvec(IntVector::new(vec![1, -20])),
+            Item::floatvec(FloatVector::new(vec![0.1, 2.0])),
+            Item::intvec(IntVector::new(vec![])),
+            Item::name("name-0a1b2c3d".to_string()),
+        ]);
+        assert_eq!(
+            PushPrinter::print(&item),
+            "( name-0a1b2c3d INT[] FLOAT[0.1,2.0] INT[1,-20] BOOL[1,0] )"
+        );
+        assert_round_trip(&item);
+    }
+
+    #[test]
+    fn print_random_code_without_loss() {
+        let mut instruction_set = InstructionSet::new();
+        instruction_set.load();
+        let instructions = instruction_set.cache();
+        let mut push_state = PushState::with_seed(3);
+        for _ in 0..20 {
+            let item = CodeGenerator::random_code_with_size(&mut push_state, &instructions, 40);
+            assert_round_trip(&item);
+        }
+    }
+
+    #[test]
+    fn print_index_and_graph_without_loss() {
+        let mut index = Index::new(7);
+        index.current = 2;
+        let mut graph = Graph::new();
+        let first_node = graph.add_node(3);
+        let second_node = graph.add_node(-4);
+        graph.add_edge(first_node, second_node, 0.25);
+        graph.add_edge(second_node, first_node, -1.5);
+        let item = Item::list(vec![
+            Item::index(index),
+            Item::Literal {
+                push_type: PushType::Graph { val: graph },
+            },
+            Item::graph(),
+        ]);
+        let code = PushPrinter::print(&item);
+        assert!(code.starts_with("( GRAPH[|] GRAPH["));
+        assert!(code.ends_with("] INDEX[2/7] )"));
+        assert_round_trip(&item);
+    }
+}
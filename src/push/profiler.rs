@@ -0,0 +1,169 @@
+use crate::push::instructions::{InstructionId, InstructionSet};
+use crate::push::interpreter::PushObserver;
+use crate::push::item::Item;
+use crate::push::state::{ChangeMark, PushState};
+use std::collections::{HashMap, HashSet};
+use std::fmt;
+use std::time::{Duration, Instant};
+
+/// Execution statistics of a single instruction.
+#[derive(Clone, Debug, PartialEq)]
+pub struct InstructionProfile {
+    pub name: String,
+    // Number of executions
+    pub calls: usize,
+    // Accumulated execution time
+    pub total_time: Duration,
+    // Number of executions without effect on the state
+    pub noops: usize,
+}
+
+impl InstructionProfile {
+    pub fn new(name: String) -> Self {
+        Self {
+            name,
+            calls: 0,
+            total_time: Duration::from_millis(0),
+            noops: 0,
+        }
+    }
+
+    /// Returns the average execution time or zero if the
+    /// instruction has not been called.
+    pub fn mean_time(&self) -> Duration {
+        if self.calls == 0 {
+            Duration::from_millis(0)
+        } else {
+            self.total_time / self.calls as u32
+        }
+    }
+}
+
+/// Observer that records call count, execution time and number of
+/// executions without effect for each instruction of the instruction set.
+/// Pass it to PushInterpreter::run_with_observer to profile a run. The
+/// statistics accumulate over multiple runs.
+pub struct Profiler {
+    instruction_ids: HashSet<InstructionId>,
+    profiles: HashMap<InstructionId, InstructionProfile>,
+    // Start time and changes of the state of the current step
+    current_step: Option<(Instant, ChangeMark)>,
+}
+
+impl Profiler {
+    pub fn new(instruction_set: &InstructionSet) -> Self {
+        Self {
+            instruction_ids: instruction_set.ids().into_iter().collect(),
+            profiles: HashMap::new(),
+            current_step: None,
+        }
+    }
+
+    /// Returns the statistics of all executed instructions sorted by
+    /// total execution time in descending order.
+    pub fn profiles(&self) -> Vec<InstructionProfile> {
+        let mut profiles: Vec<InstructionProfile> = self.profiles.values().cloned().collect();
+        profiles.sort_by(|a, b| {
+            b.total_time
+                .cmp(&a.total_time)
+                .then_with(|| a.name.cmp(&b.name))
+        });
+        profiles
+    }
+
+    /// Returns the statistics of the instruction with the given name.
+    pub fn get(&self, name: &str) -> Option<&InstructionProfile> {
+        match InstructionId::lookup(name) {
+            Some(id) => self.profiles.get(&id),
+            None => None,
+        }
+    }
+
+    /// Removes all recorded statistics.
+    pub fn clear(&mut self) {
+        self.profiles.clear();
+    }
+}
+
+impl PushObserver for Profiler {
+    fn before_step(&mut self, _step: usize, item: &Item, push_state: &PushState) {
+        self.current_step = None;
+        if let Item::InstructionMeta { id } = item {
+            if self.instruction_ids.contains(id) {
+                let changes = push_state.change_mark();
+                self.current_step = Some((Instant::now(), changes));
+            }
+        }
+    }
+
+    fn after_step(&mut self, _step: usize, item: &Item, push_state: &PushState) {
+        if let Some((start, changes_before)) = self.current_step.take() {
+            let elapsed = start.elapsed();
+            if let Item::InstructionMeta { id } = item {
+                let profile = self
+                    .profiles
+                    .entry(*id)
+                    .or_insert_with(|| InstructionProfile::new(id.name().to_string()));
+                profile.calls += 1;
+                profile.total_time += elapsed;
+                if changes_before == push_state.change_mark() {
+                    profile.noops += 1;
+                }
+            }
+        }
+    }
+}
+
+impl fmt::Display for Profiler {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        writeln!(
+            f,
+            "{:<32} {:>10} {:>14} {:>12} {:>10}",
+            "INSTRUCTION", "CALLS", "TOTAL (us)", "MEAN (ns)", "NOOPS"
+        )?;
+        for profile in self.profiles() {
+            writeln!(
+                f,
+                "{:<32} {:>10} {:>14} {:>12} {:>10}",
+                profile.name,
+                profile.calls,
+                profile.total_time.as_micros(),
+                profile.mean_time().as_nanos(),
+                profile.noops
+            )?;
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::push::interpreter::PushInterpreter;
+    use crate::push::parser::PushParser;
+
+    #[test]
+    fn profiler_counts_calls_and_noops() {
+        let input = "( 2 3 INTEGER.* INTEGER.+ INTEGER.+ )";
+        let mut push_state = PushState::new();
+        let mut instruction_set = InstructionSet::new();
+        instruction_set.load();
+        PushParser::parse_program(&mut push_state, &instruction_set, &input);
+        push_state
+            .exec_stack
+            .push(Item::instruction("UNKNOWN".to_string()));
+        let mut profiler = Profiler::new(&instruction_set);
+        PushInterpreter::run_with_observer(&mut push_state, &instruction_set, &mut profiler);
+        let multiplication = profiler.get("INTEGER.*").unwrap();
+        assert_eq!(multiplication.calls, 1);
+        assert_eq!(multiplication.noops, 0);
+        let addition = profiler.get("INTEGER.+").unwrap();
+        assert_eq!(addition.calls, 2);
+        assert_eq!(addition.noops, 2);
+        assert!(profiler.get("UNKNOWN").is_none());
+        assert_eq!(profiler.profiles().len(), 2);
+        let table = profiler.to_string();
+        assert!(table.starts_with("INSTRUCTION"));
+        assert_eq!(table.lines().count(), 3);
+    }
+}
@@ -0,0 +1,312 @@
+use crate::push::instructions::InstructionSet;
+use crate::push::item::Item;
+use crate::push::lexer::{Lexer, Token};
+use crate::push::parser::{ParseError, ParseErrorKind, PushParser};
+use crate::push::state::PushState;
+use std::collections::HashMap;
+use std::fs;
+use std::path::{Path, PathBuf};
+
+/// Program file with top-level directives on top of the Push syntax:
+///
+/// define NAME ITEM  binds the item (a token or a parenthesized list) to NAME
+/// include "PATH"    adds the definitions of another file
+///
+/// The remaining top-level code is the program. Included files can only
+/// contain definitions and includes. Include paths are relative to the
+/// directory of the including file and must not contain whitespace.
+pub struct PushProgram {
+    // Name bindings, later definitions replace earlier ones
+    pub definitions: HashMap<String, Item>,
+    pub code: Item,
+}
+
+impl PushProgram {
+    /// Parses a program. Include paths are resolved relative to base_dir.
+    pub fn parse(
+        instruction_set: &InstructionSet,
+        source: &str,
+        base_dir: &Path,
+    ) -> Result<PushProgram, ParseError> {
+        let mut definitions = HashMap::new();
+        let code = PushProgram::parse_source(
+            instruction_set,
+            source,
+            base_dir,
+            &mut vec![],
+            &mut definitions,
+            false,
+        )?;
+        Ok(PushProgram { definitions, code })
+    }
+
+    /// Reads and parses the program file at path. A file that cannot be read
+    /// is reported as BadInclude error at line 0.
+    pub fn load(instruction_set: &InstructionSet, path: &Path) -> Result<PushProgram, ParseError> {
+        let mut definitions = HashMap::new();
+        let mut include_stack = vec![];
+        let code = PushProgram::parse_file(
+            instruction_set,
+            path,
+            &mut include_stack,
+            &mut definitions,
+            false,
+        )
+        .map_err(|mut error| {
+            if error.path.is_none() {
+                error.path = Some(path.to_path_buf());
+            }
+            error
+        })?;
+        Ok(PushProgram { definitions, code })
+    }
+
+    /// Adds the definitions to the name bindings of the state and pushes
+    /// the program onto the EXEC stack.
+    pub fn load_into(&self, push_state: &mut PushState) {
+        for (name, item) in self.definitions.iter() {
+            push_state.name_bindings.insert(name.clone(), item.clone());
+        }
+        push_state.exec_stack.push(self.code.clone());
+    }
+
+    fn parse_file(
+        instruction_set: &InstructionSet,
+        path: &Path,
+        include_stack: &mut Vec<PathBuf>,
+        definitions: &mut HashMap<String, Item>,
+        is_include: bool,
+    ) -> Result<Item, ParseError> {
+        let unreadable = || {
+            let mut error =
+                ParseError::new(0, 0, &path.to_string_lossy(), ParseErrorKind::BadInclude);
+            error.path = Some(path.to_path_buf());
+            error
+        };
+        let canonical_path = fs::canonicalize(path).map_err(|_| unreadable())?;
+        let source = fs::read_to_string(&canonical_path).map_err(|_| unreadable())?;
+        let base_dir = canonical_path
+            .parent()
+            .map_or(PathBuf::new(), |dir| dir.to_path_buf());
+        include_stack.push(canonical_path.clone());
+        let code = PushProgram::parse_source(
+            instruction_set,
+            &source,
+            &base_dir,
+            include_stack,
+            definitions,
+            is_include,
+        )
+        .map_err(|mut error| {
+            if error.path.is_none() {
+                error.path = Some(canonical_path.clone());
+            }
+            error
+        });
+        include_stack.pop();
+        code
+    }
+
+    fn parse_source(
+        instruction_set: &InstructionSet,
+        source: &str,
+        base_dir: &Path,
+        include_stack: &mut Vec<PathBuf>,
+        definitions: &mut HashMap<String, Item>,
+        is_include: bool,
+    ) -> Result<Item, ParseError> {
+        let tokens = Lexer::tokenize(source)?;
+        let error = |token: &Token, kind: ParseErrorKind| {
+            ParseError::new(token.line, token.column, token.text, kind)
+        };
+        let mut code_tokens = vec![];
+        let mut i = 0;
+        while i < tokens.len() {
+            let token = &tokens[i];
+            match token.text {
+                "define" => {
+                    let name = match tokens.get(i + 1) {
+                        Some(name) if name.text != "(" && name.text != ")" => name.text,
+                        _ => return Err(error(token, ParseErrorKind::BadDefinition)),
+                    };
+                    if i + 2 >= tokens.len() {
+                        return Err(error(token, ParseErrorKind::BadDefinition));
+                    }
+                    let end = PushProgram::item_end(&tokens, i + 2)?;
+                    let item =
+                        PushParser::parse_tokens(instruction_set, tokens[i + 2..end].to_vec())?;
+                    definitions.insert(name.to_string(), item);
+                    i = end;
+                }
+                "include" => {
+                    let path = match tokens.get(i + 1) {
+                        Some(path)
+                            if path.text.len() >= 2
+                                && path.text.starts_with('"')
+                                && path.text.ends_with('"') =>
+                        {
+                            path
+                        }
+                        _ => return Err(error(token, ParseErrorKind::BadInclude)),
+                    };
+                    let include_path = base_dir.join(&path.text[1..path.text.len() - 1]);
+                    let is_cyclic = match fs::canonicalize(&include_path) {
+                        Ok(canonical_path) => include_stack.contains(&canonical_path),
+                        Err(_) => return Err(error(path, ParseErrorKind::BadInclude)),
+                    };
+                    if is_cyclic {
+                        return Err(error(path, ParseErrorKind::BadInclude));
+                    }
+                    PushProgram::parse_file(
+                        instruction_set,
+                        &include_path,
+                        include_stack,
+                        definitions,
+                        true,
+                    )?;
+                    i += 2;
+                }
+                _ => {
+                    if is_include {
+                        return Err(error(token, ParseErrorKind::BadInclude));
+                    }
+                    let end = PushProgram::item_end(&tokens, i)?;
+                    code_tokens.extend_from_slice(&tokens[i..end]);
+                    i = end;
+                }
+            }
+        }
+        PushParser::parse_tokens(instruction_set, code_tokens)
+    }
+
+    /// Returns the index after the item that starts at index start, i.e. after
+    /// the closing parenthesis if the item is a list.
+    fn item_end(tokens: &[Token], start: usize) -> Result<usize, ParseError> {
+        let mut depth = 0;
+        for (i, token) in tokens.iter().enumerate().skip(start) {
+            match token.text {
+                "(" => depth += 1,
+                ")" => {
+                    if depth == 0 {
+                        return Err(ParseError::new(
+                            token.line,
+                            token.column,
+                            token.text,
+                            ParseErrorKind::UnbalancedParentheses,
+                        ));
+                    }
+                    depth -= 1;
+                }
+                _ => (),
+            }
+            if depth == 0 {
+                return Ok(i + 1);
+            }
+        }
+        let token = &tokens[start];
+        Err(ParseError::new(
+            token.line,
+            token.column,
+            token.text,
+            ParseErrorKind::UnbalancedParentheses,
+        ))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::push::interpreter::PushInterpreter;
+    use std::env;
+
+    /// Creates an empty directory for the files of a test.
+    fn test_dir(name: &str) -> PathBuf {
+        let dir = env::temp_dir().join(format!("pushr-program-{}-{}", name, std::process::id()));
+        let _ = fs::remove_dir_all(&dir);
+        fs::create_dir_all(&dir).unwrap();
+        dir
+    }
+
+    #[test]
+    fn definitions_are_bound_before_execution() {
+        let mut instruction_set = InstructionSet::new();
+        instruction_set.load();
+        let source = "define SQUARE ( INTEGER.DUP INTEGER.* )
+                      define THREE 3
+                      ( THREE SQUARE )";
+        let program = PushProgram::parse(&instruction_set, source, Path::new(".")).unwrap();
+        assert_eq!(program.definitions.len(), 2);
+        assert_eq!(program.code.to_string(), "( THREE SQUARE )");
+        let mut push_state = PushState::new();
+        program.load_into(&mut push_state);
+        PushInterpreter::run(&mut push_state, &instruction_set);
+        assert_eq!(push_state.int_stack.to_string(), "9");
+    }
+
+    #[test]
+    fn definition_without_item_is_reported() {
+        let mut instruction_set = InstructionSet::new();
+        instruction_set.load();
+        let error = PushProgram::parse(&instruction_set, "1 define X", Path::new("."))
+            .err()
+            .unwrap();
+        assert_eq!(error.kind, ParseErrorKind::BadDefinition);
+        assert_eq!((error.line, error.column), (1, 3));
+        let error = PushProgram::parse(&instruction_set, "define X ( 1", Path::new("."))
+            .err()
+            .unwrap();
+        assert_eq!(error.kind, ParseErrorKind::UnbalancedParentheses);
+    }
+
+    #[test]
+    fn included_definitions_are_bound() {
+        let mut instruction_set = InstructionSet::new();
+        instruction_set.load();
+        let dir = test_dir("include");
+        fs::create_dir_all(dir.join("lib")).unwrap();
+        fs::write(
+            dir.join("lib").join("math.push"),
+            "; Math library\ndefine SQUARE ( INTEGER.DUP INTEGER.* )\ninclude \"constants.push\"",
+        )
+        .unwrap();
+        fs::write(dir.join("lib").join("constants.push"), "define FOUR 4").unwrap();
+        fs::write(
+            dir.join("main.push"),
+            "include \"lib/math.push\"\n( FOUR SQUARE )",
+        )
+        .unwrap();
+        let program = PushProgram::load(&instruction_set, &dir.join("main.push")).unwrap();
+        let mut push_state = PushState::new();
+        program.load_into(&mut push_state);
+        PushInterpreter::run(&mut push_state, &instruction_set);
+        assert_eq!(push_state.int_stack.to_string(), "16");
+        let _ = fs::remove_dir_all(&dir);
+    }
+
+    #[test]
+    fn bad_includes_are_reported() {
+        let mut instruction_set = InstructionSet::new();
+        instruction_set.load();
+        let dir = test_dir("bad-include");
+        fs::write(dir.join("a.push"), "include \"b.push\"").unwrap();
+        fs::write(dir.join("b.push"), "define X 1\n  include \"a.push\"").unwrap();
+        fs::write(dir.join("code.push"), "define X 1 ( X )").unwrap();
+        let error = PushProgram::load(&instruction_set, &dir.join("a.push"))
+            .err()
+            .unwrap();
+        assert_eq!(error.kind, ParseErrorKind::BadInclude);
+        assert_eq!((error.line, error.column), (2, 11));
+        assert!(error.path.unwrap().ends_with("b.push"));
+        let error = PushProgram::parse(&instruction_set, "include \"code.push\"", &dir)
+            .err()
+            .unwrap();
+        assert_eq!(error.kind, ParseErrorKind::BadInclude);
+        assert_eq!(error.token, "(");
+        let error = PushProgram::parse(&instruction_set, "include \"missing.push\"", &dir)
+            .err()
+            .unwrap();
+        assert_eq!(error.kind, ParseErrorKind::BadInclude);
+        assert_eq!(error.token, "\"missing.push\"");
+        let _ = fs::remove_dir_all(&dir);
+    }
+}
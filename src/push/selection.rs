@@ -0,0 +1,269 @@
+use crate::push::evolution::Individual;
+use crate::push::state::PushState;
+use rand::seq::SliceRandom;
+use rand::Rng;
+use std::cmp::Ordering;
+
+/// Strategy to select parents from an evaluated population. All random
+/// values are drawn from the random number generator of the push state.
+pub trait Selector {
+    /// Called once per generation before the parents are selected,
+    /// e.g. to precalculate values that depend on the whole population.
+    fn prepare(&mut self, _push_state: &mut PushState, _population: &[Individual]) {}
+
+    /// Returns the selected parent.
+    fn select<'a>(
+        &mut self,
+        push_state: &mut PushState,
+        population: &'a [Individual],
+    ) -> &'a Individual;
+}
+
+/// Selects the individual with the lowest total error among size random
+/// individuals.
+pub struct Tournament {
+    pub size: usize,
+}
+
+impl Tournament {
+    pub fn new(size: usize) -> Self {
+        Self { size }
+    }
+}
+
+impl Selector for Tournament {
+    fn select<'a>(
+        &mut self,
+        push_state: &mut PushState,
+        population: &'a [Individual],
+    ) -> &'a Individual {
+        let mut best = population.choose(&mut push_state.rng).unwrap();
+        for _ in 1..self.size {
+            let candidate = population.choose(&mut push_state.rng).unwrap();
+            if candidate.compare(best) == Ordering::Less {
+                best = candidate;
+            }
+        }
+        best
+    }
+}
+
+/// Filters the population by the cases in random order. Each case keeps
+/// the individuals with the lowest error on it until a single individual
+/// is left or all cases have been used.
+pub struct Lexicase {}
+
+impl Lexicase {
+    pub fn new() -> Self {
+        Self {}
+    }
+}
+
+impl Default for Lexicase {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl Selector for Lexicase {
+    fn select<'a>(
+        &mut self,
+        push_state: &mut PushState,
+        population: &'a [Individual],
+    ) -> &'a Individual {
+        let cases: Vec<usize> = (0..population[0].errors.len()).collect();
+        lexicase(push_state, population, cases, &[])
+    }
+}
+
+/// Lexicase selection for continuous errors. Individuals whose error on a
+/// case is within epsilon of the lowest error pass the case. Epsilon is the
+/// median absolute deviation of the errors of the population on the case.
+pub struct EpsilonLexicase {
+    // Epsilon for each case of the current generation
+    pub epsilons: Vec<f32>,
+}
+
+impl EpsilonLexicase {
+    pub fn new() -> Self {
+        Self { epsilons: vec![] }
+    }
+}
+
+impl Default for EpsilonLexicase {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl Selector for EpsilonLexicase {
+    fn prepare(&mut self, _push_state: &mut PushState, population: &[Individual]) {
+        self.epsilons = (0..population[0].errors.len())
+            .map(|case| {
+                let errors: Vec<f32> = population.iter().map(|i| i.errors[case]).collect();
+                let center = median(errors.clone());
+                let epsilon = median(errors.iter().map(|e| (e - center).abs()).collect());
+                if epsilon.is_finite() {
+                    epsilon
+                } else {
+                    0.0
+                }
+            })
+            .collect();
+    }
+
+    fn select<'a>(
+        &mut self,
+        push_state: &mut PushState,
+        population: &'a [Individual],
+    ) -> &'a Individual {
+        let cases: Vec<usize> = (0..population[0].errors.len()).collect();
+        lexicase(push_state, population, cases, &self.epsilons)
+    }
+}
+
+/// Lexicase selection on a random subset of the cases that is drawn once
+/// per generation. The rate is the fraction of cases in the subset.
+pub struct DownsampledLexicase {
+    pub rate: f32,
+    // Cases of the current generation
+    pub cases: Vec<usize>,
+}
+
+impl DownsampledLexicase {
+    pub fn new(rate: f32) -> Self {
+        Self {
+            rate,
+            cases: vec![],
+        }
+    }
+}
+
+impl Selector for DownsampledLexicase {
+    fn prepare(&mut self, push_state: &mut PushState, population: &[Individual]) {
+        let num_cases = population[0].errors.len();
+        let amount = usize::min(
+            usize::max((self.rate * num_cases as f32).round() as usize, 1),
+            num_cases,
+        );
+        self.cases = rand::seq::index::sample(&mut push_state.rng, num_cases, amount).into_vec();
+    }
+
+    fn select<'a>(
+        &mut self,
+        push_state: &mut PushState,
+        population: &'a [Individual],
+    ) -> &'a Individual {
+        lexicase(push_state, population, self.cases.clone(), &[])
+    }
+}
+
+/// Returns a random individual among those that pass the cases in random
+/// order. Epsilons are indexed by case, missing epsilons are zero.
+fn lexicase<'a>(
+    push_state: &mut PushState,
+    population: &'a [Individual],
+    mut cases: Vec<usize>,
+    epsilons: &[f32],
+) -> &'a Individual {
+    cases.shuffle(&mut push_state.rng);
+    let mut candidates: Vec<&Individual> = population.iter().collect();
+    for case in cases {
+        if candidates.len() <= 1 {
+            break;
+        }
+        let best = candidates
+            .iter()
+            .map(|i| i.errors[case])
+            .min_by(|a, b| a.total_cmp(b))
+            .unwrap();
+        let epsilon = epsilons.get(case).copied().unwrap_or(0.0);
+        candidates.retain(|i| i.errors[case] == best || i.errors[case] <= best + epsilon);
+    }
+    candidates[push_state.rng.gen_range(0..candidates.len())]
+}
+
+/// Returns the middle value or the mean of the two middle values for an
+/// even number of values.
+fn median(mut values: Vec<f32>) -> f32 {
+    values.sort_by(|a, b| a.total_cmp(b));
+    let middle = values.len() / 2;
+    if values.len() % 2 == 1 {
+        values[middle]
+    } else {
+        (values[middle - 1] + values[middle]) / 2.0
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::push::item::Item;
+
+    fn population(errors: Vec<Vec<f32>>) -> Vec<Individual> {
+        errors
+            .into_iter()
+            .enumerate()
+            .map(|(i, e)| Individual::new(Item::int(i as i32), e))
+            .collect()
+    }
+
+    fn selections<S: Selector>(selector: &mut S, population: &[Individual]) -> Vec<usize> {
+        let mut push_state = PushState::with_seed(1);
+        let mut counts = vec![0; population.len()];
+        selector.prepare(&mut push_state, population);
+        for _ in 0..100 {
+            let selected = selector.select(&mut push_state, population);
+            counts[selected.program.to_string().parse::<usize>().unwrap()] += 1;
+        }
+        counts
+    }
+
+    #[test]
+    fn tournament_prefers_lower_total_error() {
+        let population = population(vec![vec![3.0], vec![1.0], vec![2.0]]);
+        let counts = selections(&mut Tournament::new(30), &population);
+        assert_eq!(counts, vec![0, 100, 0]);
+        let counts = selections(&mut Tournament::new(1), &population);
+        assert!(counts.iter().all(|c| *c > 0));
+    }
+
+    #[test]
+    fn lexicase_selects_specialists() {
+        // The generalist has the lowest total error but is never best on a case
+        let population = population(vec![vec![0.0, 10.0], vec![10.0, 0.0], vec![4.0, 4.0]]);
+        let counts = selections(&mut Lexicase::new(), &population);
+        assert_eq!(counts[2], 0);
+        assert!(counts[0] > 0 && counts[1] > 0);
+        let counts = selections(&mut Tournament::new(30), &population);
+        assert_eq!(counts[2], 100);
+    }
+
+    #[test]
+    fn epsilon_lexicase_keeps_individuals_within_epsilon() {
+        let population = population(vec![vec![0.0, 0.0], vec![0.1, 0.1], vec![0.2, 0.2]]);
+        let mut selector = EpsilonLexicase::new();
+        let counts = selections(&mut selector, &population);
+        assert!((selector.epsilons[0] - 0.1).abs() < 1e-6);
+        assert_eq!(counts[2], 0);
+        assert!(counts[0] > 0 && counts[1] > 0);
+        let counts = selections(&mut Lexicase::new(), &population);
+        assert_eq!(counts[0], 100);
+    }
+
+    #[test]
+    fn median_averages_middle_values() {
+        assert_eq!(median(vec![3.0, 1.0, 2.0]), 2.0);
+        assert_eq!(median(vec![10.0, 1.0, 3.0, 2.0]), 2.5);
+        assert_eq!(median(vec![4.0]), 4.0);
+    }
+
+    #[test]
+    fn downsampled_lexicase_uses_subset_of_cases() {
+        let population = population(vec![vec![0.0, 10.0], vec![10.0, 0.0]]);
+        let mut selector = DownsampledLexicase::new(0.5);
+        let counts = selections(&mut selector, &population);
+        assert_eq!(selector.cases.len(), 1);
+        assert_eq!(counts[selector.cases[0]], 100);
+    }
+}
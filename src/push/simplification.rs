@@ -0,0 +1,212 @@
+use crate::push::batch::{BatchResult, BatchRunner};
+use crate::push::evolution::Individual;
+use crate::push::instructions::InstructionSet;
+use crate::push::item::Item;
+use crate::push::state::PushState;
+use rand::Rng;
+
+/// Removes dead code from evolved programs. Each step removes random
+/// subtrees from the current program and keeps the result if its error
+/// does not get worse on any case. Programs are run by the batch runner
+/// with the configuration of the push state and a seed drawn once per
+/// simplification, so that all candidates see the same random values.
+pub struct Simplifier {
+    // Number of simplification attempts
+    pub steps: usize,
+    // Maximum number of subtrees removed in a single attempt
+    pub max_removals: usize,
+    pub runner: BatchRunner,
+}
+
+impl Simplifier {
+    pub fn new(steps: usize) -> Self {
+        Self {
+            steps,
+            max_removals: 2,
+            runner: BatchRunner::with_available_parallelism(),
+        }
+    }
+
+    /// Returns the last accepted program together with its errors. Since
+    /// Item::remove_at always shrinks the program, each accepted candidate
+    /// is smaller than the previous one. Random values are drawn from the random number
+    /// generator of the push state. Setup and error are used as in
+    /// Evolution::run.
+    pub fn simplify<C, S, E>(
+        &mut self,
+        push_state: &mut PushState,
+        instruction_set: &InstructionSet,
+        program: &Item,
+        cases: &[C],
+        setup: S,
+        error: E,
+    ) -> Individual
+    where
+        S: Fn(&mut PushState, &C) + Sync,
+        C: Sync,
+        E: Fn(&BatchResult, &C) -> f32,
+    {
+        self.runner.configuration = push_state.configuration.clone();
+        self.runner.seed = Some(push_state.rng.gen());
+        let mut best = self.evaluate(program.clone(), instruction_set, cases, &setup, &error);
+        for _ in 0..self.steps {
+            if Item::size(&best.program) <= 1 {
+                break;
+            }
+            let mut candidate = best.program.clone();
+            let removals = push_state
+                .rng
+                .gen_range(1..=usize::max(self.max_removals, 1));
+            for _ in 0..removals {
+                let size = Item::size(&candidate);
+                if size <= 1 {
+                    break;
+                }
+                let point = push_state.rng.gen_range(1..size);
+                candidate = Item::remove_at(&candidate, point).unwrap();
+            }
+            let candidate = self.evaluate(candidate, instruction_set, cases, &setup, &error);
+            assert_eq!(candidate.errors.len(), best.errors.len());
+            let not_worse = candidate
+                .errors
+                .iter()
+                .zip(&best.errors)
+                .all(|(c, b)| c <= b);
+            if not_worse {
+                best = candidate;
+            }
+        }
+        best
+    }
+
+    fn evaluate<C, S, E>(
+        &self,
+        program: Item,
+        instruction_set: &InstructionSet,
+        cases: &[C],
+        setup: S,
+        error: E,
+    ) -> Individual
+    where
+        S: Fn(&mut PushState, &C) + Sync,
+        C: Sync,
+        E: Fn(&BatchResult, &C) -> f32,
+    {
+        let results = self.runner.run_items(
+            std::slice::from_ref(&program),
+            cases,
+            instruction_set,
+            setup,
+        );
+        let errors = results[0]
+            .iter()
+            .zip(cases)
+            .map(|(result, case)| error(result, case))
+            .collect();
+        Individual::new(program, errors)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::push::parser::PushParser;
+
+    /// Pushes the input x of the case.
+    fn push_input(push_state: &mut PushState, x: &i32) {
+        push_state.int_stack.push(*x);
+    }
+
+    /// Distance of the top INTEGER to x * x.
+    fn square_error(result: &BatchResult, x: &i32) -> f32 {
+        match result.state.int_stack.copy(0) {
+            Some(val) => (val - x * x).abs() as f32,
+            None => 1000.0,
+        }
+    }
+
+    #[test]
+    fn simplify_removes_dead_code() {
+        let mut instruction_set = InstructionSet::new();
+        instruction_set.load();
+        let program = PushParser::parse(
+            &instruction_set,
+            "( TRUE ( INTEGER.DUP 2.5 ) ( FLOAT.DUP FLOAT.+ ) INTEGER.* ( 5 INTEGER.POP ) BOOLEAN.NOT )",
+        )
+        .unwrap();
+        let cases: Vec<i32> = (-3..4).collect();
+        let mut push_state = PushState::with_seed(1);
+        let simplified = Simplifier::new(200).simplify(
+            &mut push_state,
+            &instruction_set,
+            &program,
+            &cases,
+            push_input,
+            square_error,
+        );
+        assert_eq!(simplified.total_error, 0.0);
+        assert_eq!(
+            simplified.program.to_string(),
+            "( ( INTEGER.DUP ) INTEGER.* )"
+        );
+    }
+
+    #[test]
+    fn simplify_keeps_errors() {
+        let mut instruction_set = InstructionSet::new();
+        instruction_set.load();
+        let program =
+            PushParser::parse(&instruction_set, "( INTEGER.DUP INTEGER.+ 1 INTEGER.- )").unwrap();
+        let cases: Vec<i32> = (0..5).collect();
+        let mut push_state = PushState::with_seed(2);
+        let mut simplifier = Simplifier::new(100);
+        let original = simplifier.evaluate(
+            program.clone(),
+            &instruction_set,
+            &cases,
+            push_input,
+            square_error,
+        );
+        let simplified = simplifier.simplify(
+            &mut push_state,
+            &instruction_set,
+            &program,
+            &cases,
+            push_input,
+            square_error,
+        );
+        assert!(Item::size(&simplified.program) <= Item::size(&program));
+        for (s, o) in simplified.errors.iter().zip(&original.errors) {
+            assert!(s <= o);
+        }
+    }
+
+    #[test]
+    fn simplify_is_reproducible_for_seeded_state() {
+        let mut instruction_set = InstructionSet::new();
+        instruction_set.load();
+        let program = PushParser::parse(
+            &instruction_set,
+            "( INTEGER.DUP INTEGER.* INTEGER.RAND INTEGER.+ ( INTEGER.RAND INTEGER.POP ) INTEGER.RAND )",
+        )
+        .unwrap();
+        let cases: Vec<i32> = (0..5).collect();
+        let simplify = || {
+            let mut push_state = PushState::with_seed(3);
+            push_state.configuration.max_random_integer = 3;
+            push_state.configuration.min_random_integer = -3;
+            Simplifier::new(50).simplify(
+                &mut push_state,
+                &instruction_set,
+                &program,
+                &cases,
+                push_input,
+                square_error,
+            )
+        };
+        let first = simplify();
+        let second = simplify();
+        assert_eq!(first.program.to_string(), second.program.to_string());
+        assert_eq!(first.errors, second.errors);
+    }
+}
@@ -1,8 +1,26 @@
 use std::fmt;
 
 #[derive(Clone, Debug)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub struct PushStack<T> {
     elements: Vec<T>,
+    // Number of modifications since creation
+    changes: usize,
+    // Previous elements while a delta is recorded, see begin_delta
+    #[cfg_attr(feature = "serde", serde(skip, default = "Option::default"))]
+    delta: Option<StackDelta<T>>,
+}
+
+/// Previous content of the modified part of a stack. Elements at the
+/// bottom of the stack that have not been modified are not stored.
+#[derive(Clone, Debug)]
+pub struct StackDelta<T> {
+    // Number of elements at the bottom that have not been modified
+    unmodified: usize,
+    // Previous elements above the unmodified ones
+    elements: Vec<T>,
+    // Previous number of modifications
+    changes: usize,
 }
 
 pub trait PushPrint {
@@ -40,13 +58,19 @@ where
     pub fn new() -> Self {
         Self {
             elements: Vec::new(),
+            changes: 0,
+            delta: None,
         }
     }
 
     /// Initializes the stack with the argument. Its last
     /// element becomes the top element of the stack.
     pub fn from_vec(elements: Vec<T>) -> Self {
-        Self { elements: elements }
+        Self {
+            elements: elements,
+            changes: 0,
+            delta: None,
+        }
     }
 
     /// Prints the stack from top to bottom enumerating its elements.
@@ -58,6 +82,52 @@ where
         result.trim().to_string()
     }
 
+    /// Returns the number of modifications since the creation of the stack.
+    /// Methods that hand out mutable references count as a modification.
+    pub fn changes(&self) -> usize {
+        self.changes
+    }
+
+    /// Starts recording the previous content of all elements that are modified
+    /// until end_delta is called.
+    pub fn begin_delta(&mut self) {
+        self.delta = Some(StackDelta {
+            unmodified: self.elements.len(),
+            elements: vec![],
+            changes: self.changes,
+        });
+    }
+
+    /// Stops recording and returns the previous content of the modified
+    /// elements or None if the stack has not been modified.
+    pub fn end_delta(&mut self) -> Option<StackDelta<T>> {
+        match self.delta.take() {
+            Some(delta) if delta.changes != self.changes => Some(delta),
+            _ => None,
+        }
+    }
+
+    /// Restores the stack content before the delta has been recorded.
+    pub fn revert(&mut self, delta: StackDelta<T>) {
+        self.elements.truncate(delta.unmodified);
+        self.elements.extend(delta.elements);
+        self.changes = delta.changes;
+    }
+
+    /// Counts a modification of the elements from index from (counting from the
+    /// bottom) upwards and records their previous content if a delta is recorded.
+    fn modify(&mut self, from: usize) {
+        self.changes += 1;
+        if let Some(delta) = &mut self.delta {
+            if from < delta.unmodified {
+                let mut previous = self.elements[from..delta.unmodified].to_vec();
+                previous.append(&mut delta.elements);
+                delta.elements = previous;
+                delta.unmodified = from;
+            }
+        }
+    }
+
     /// Returns the stack size.
     pub fn size(&self) -> usize {
         return self.elements.len();
@@ -85,6 +155,7 @@ where
     /// of the stack.
     pub fn bottom_mut(&mut self) -> Option<&mut T> {
         if self.size() > 0 {
+            self.modify(0);
             self.elements.first_mut()
         } else {
             None
@@ -93,6 +164,7 @@ where
 
     /// Removes all elements from the stack.
     pub fn flush(&mut self) {
+        self.modify(0);
         self.elements = Vec::new();
     }
 
@@ -102,6 +174,7 @@ where
         let size = &mut self.size();
         match i.checked_sub(*size) {
             None => {
+                self.modify(*size - (i + 1));
                 let _ = std::mem::replace(&mut self.elements[*size - (i + 1)], new_el);
                 Ok(())
             }
@@ -113,12 +186,14 @@ where
     pub fn remove(&mut self, i: usize) {
         let size = self.size();
         if i < size {
+            self.modify(size - (i + 1));
             self.elements.remove(size - (i + 1));
         }
     }
 
     /// Reverse elements of stack.
     pub fn reverse(&mut self) {
+        self.modify(0);
         self.elements.reverse();
     }
 
@@ -127,6 +202,7 @@ where
     pub fn get_mut(&mut self, i: usize) -> Option<&mut T> {
         let size = &mut self.size();
         if i < *size {
+            self.modify(*size - (i + 1));
             Some(&mut self.elements[*size - (i + 1)])
         } else {
             None
@@ -146,11 +222,13 @@ where
 
     /// Pushes element to the top of the stack.
     pub fn push(&mut self, value: T) {
+        self.modify(self.elements.len());
         self.elements.push(value);
     }
 
     /// Pushes element to the bottom of the stack.
     pub fn push_front(&mut self, value: T) {
+        self.modify(0);
         self.elements.insert(0, value);
     }
 
@@ -158,6 +236,7 @@ where
     /// pushes it on top of the stack.
     pub fn yank(&mut self, index: usize) {
         if index > 0 && index < self.size() {
+            self.modify(self.size() - (index + 1));
             let el = self.elements.remove(self.size() - (index + 1));
             self.elements.push(el);
         }
@@ -167,6 +246,7 @@ where
     /// position index counting from the top.
     pub fn shove(&mut self, index: usize) {
         if index > 0 && index < self.size() {
+            self.modify(self.size() - (index + 1));
             if let Some(el) = self.elements.pop() {
                 let top_down_index = self.size() - index;
                 self.elements.insert(top_down_index, el);
@@ -176,6 +256,7 @@ where
 
     /// Swaps vector elements
     pub fn swap(&mut self, i: usize, j: usize) {
+        self.modify(usize::min(i, j));
         self.elements.swap(i, j);
     }
 
@@ -184,11 +265,16 @@ where
         if self.elements.is_empty() {
             return None;
         }
+        self.modify(0);
         Some(self.elements.remove(0))
     }
 
     /// Removes the top element from the stack and returns it.
     pub fn pop(&mut self) -> Option<T> {
+        if self.elements.is_empty() {
+            return None;
+        }
+        self.modify(self.elements.len() - 1);
         self.elements.pop()
     }
 
@@ -199,6 +285,7 @@ where
         if n > self.elements.len() {
             None
         } else {
+            self.modify(self.elements.len() - n);
             Some(
                 self.elements
                     .split_off(self.elements.len() - n)
@@ -236,6 +323,9 @@ where
     /// Pushes the argument to the stack where the last
     /// element of the argument will at the top of the stack.
     pub fn push_vec(&mut self, to_push: Vec<T>) {
+        if !to_push.is_empty() {
+            self.modify(self.elements.len());
+        }
         self.elements.extend(to_push);
     }
 }
@@ -246,9 +336,7 @@ mod tests {
 
     #[test]
     fn pop_vec_in_right_order() {
-        let mut test_stack = PushStack {
-            elements: vec![1, 2, 3],
-        };
+        let mut test_stack = PushStack::from_vec(vec![1, 2, 3]);
 
         match test_stack.pop_vec(2) {
             None => assert!(false),
@@ -258,9 +346,7 @@ mod tests {
 
     #[test]
     fn pop_vec_max_index() {
-        let mut test_stack = PushStack {
-            elements: vec![1, 2, 3],
-        };
+        let mut test_stack = PushStack::from_vec(vec![1, 2, 3]);
         match test_stack.pop_vec(4) {
             None => assert!(true),
             Some(_pv) => assert!(false),
@@ -269,9 +355,7 @@ mod tests {
 
     #[test]
     fn push_vec_in_right_order() {
-        let mut test_stack = PushStack {
-            elements: vec![1, 2, 3],
-        };
+        let mut test_stack = PushStack::from_vec(vec![1, 2, 3]);
         let test_vec = vec![4, 5];
         test_stack.push_vec(test_vec);
         assert_eq!(test_stack.elements, [1, 2, 3, 4, 5]);
@@ -279,9 +363,7 @@ mod tests {
 
     #[test]
     fn copy_vec_preserves_stack() {
-        let test_stack = PushStack {
-            elements: vec![1, 2, 3],
-        };
+        let test_stack = PushStack::from_vec(vec![1, 2, 3]);
 
         match test_stack.copy_vec(2) {
             None => assert!(false, "Should return values"),
@@ -300,9 +382,7 @@ mod tests {
 
     #[test]
     fn equal_at_checks_equality_at_right_index() {
-        let test_stack = PushStack {
-            elements: vec![1, 2, 3, 4, 5],
-        };
+        let test_stack = PushStack::from_vec(vec![1, 2, 3, 4, 5]);
         assert_eq!(test_stack.equal_at(0, &5), Some(true));
         assert_eq!(test_stack.equal_at(3, &2), Some(true));
         assert_eq!(test_stack.equal_at(3, &1), Some(false));
@@ -310,9 +390,7 @@ mod tests {
 
     #[test]
     fn yank_vec_returns_right_order() {
-        let mut test_stack = PushStack {
-            elements: vec![1, 2, 3, 4, 5],
-        };
+        let mut test_stack = PushStack::from_vec(vec![1, 2, 3, 4, 5]);
         let mut test_idx = 1;
         test_stack.yank(test_idx);
         assert_eq!(test_stack.elements, [1, 2, 3, 5, 4]);
@@ -329,9 +407,7 @@ mod tests {
 
     #[test]
     fn shove_vec_returns_right_order() {
-        let mut test_stack = PushStack {
-            elements: vec![1, 2, 3, 4, 5],
-        };
+        let mut test_stack = PushStack::from_vec(vec![1, 2, 3, 4, 5]);
         let mut test_idx = 1;
         test_stack.shove(test_idx);
         assert_eq!(test_stack.elements, [1, 2, 3, 5, 4]);
@@ -348,25 +424,19 @@ mod tests {
 
     #[test]
     fn last_eq_preserves_vector() {
-        let test_stack = PushStack {
-            elements: vec![1, 2, 3, 4, 5],
-        };
+        let test_stack = PushStack::from_vec(vec![1, 2, 3, 4, 5]);
         let candidate = 5;
         assert_eq!(test_stack.last_eq(&candidate), true);
         let candidate = 4;
         assert_eq!(test_stack.last_eq(&candidate), false);
         assert_eq!(test_stack.size(), 5);
-        let test_stack = PushStack {
-            elements: Vec::new(),
-        };
+        let test_stack = PushStack::from_vec(Vec::new());
         assert_eq!(test_stack.last_eq(&candidate), false);
     }
 
     #[test]
     fn replace_returns_right_offset() {
-        let mut test_stack = PushStack {
-            elements: vec![1, 2, 3, 4, 5],
-        };
+        let mut test_stack = PushStack::from_vec(vec![1, 2, 3, 4, 5]);
         assert_eq!(test_stack.replace(1, 19), Ok(()));
         assert_eq!(test_stack.replace(5, 19), Err(1));
         assert_eq!(test_stack.replace(6, 19), Err(2));
@@ -375,12 +445,42 @@ mod tests {
         assert_eq!(test_stack.to_string(), "19 19 3 2 19");
     }
 
+    #[test]
+    fn changes_ignore_operations_without_effect() {
+        let mut test_stack: PushStack<i32> = PushStack::new();
+        assert_eq!(test_stack.pop(), None);
+        assert_eq!(test_stack.pop_vec(1), None);
+        test_stack.yank(1);
+        assert_eq!(test_stack.changes(), 0);
+        test_stack.push(1);
+        test_stack.push(2);
+        test_stack.shove(1);
+        test_stack.pop();
+        assert_eq!(test_stack.changes(), 4);
+    }
+
     #[test]
     fn reverse_elements() {
-        let mut test_stack = PushStack {
-            elements: vec![1, 2, 3, 4, 5],
-        };
+        let mut test_stack = PushStack::from_vec(vec![1, 2, 3, 4, 5]);
         test_stack.reverse();
         assert_eq!(test_stack.elements, [5, 4, 3, 2, 1]);
     }
+
+    #[test]
+    fn revert_restores_modified_elements() {
+        let mut test_stack = PushStack::from_vec(vec![1, 2, 3, 4, 5]);
+        test_stack.begin_delta();
+        test_stack.pop();
+        test_stack.push(6);
+        test_stack.yank(2);
+        test_stack.push(7);
+        let delta = test_stack.end_delta().unwrap();
+        assert_eq!(delta.unmodified, 2);
+        assert_eq!(test_stack.to_string(), "7 3 6 4 2 1");
+        test_stack.revert(delta);
+        assert_eq!(test_stack.elements, [1, 2, 3, 4, 5]);
+        assert_eq!(test_stack.changes(), 0);
+        test_stack.begin_delta();
+        assert!(test_stack.end_delta().is_none());
+    }
 }
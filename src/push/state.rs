@@ -5,9 +5,13 @@ use crate::push::item::Item;
 use crate::push::stack::PushStack;
 use crate::push::buffer::{PushBuffer, BufferType};
 use crate::push::io::{PushMessage};
+use crate::push::journal::Journal;
 use crate::push::vector::{BoolVector, FloatVector, IntVector};
+use rand::SeedableRng;
+use rand_chacha::ChaCha8Rng;
 use std::collections::HashMap;
 use std::fmt;
+use std::time::Duration;
 
 pub const BOOL_STACK_ID: i32 = 1;
 pub const BOOL_VECTOR_STACK_ID: i32 = 2;
@@ -27,11 +31,20 @@ pub const INPUT_BUFFER_SIZE: usize = 10;
 pub const OUTPUT_BUFFER_SIZE: usize = 3;
 pub const GRAPH_BUFFER_SIZE: usize = 100;
 
+/// Change counters and flags of a state. An instruction had no effect if the
+/// marks before and after its execution are equal.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct ChangeMark {
+    changes: usize,
+    quote_name: bool,
+    send_name: bool,
+}
 
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub struct PushState {
     // Scalar Types
     pub bool_stack: PushStack<bool>,
-    pub code_stack: PushStack<Item>, // Change this to reference for speedup
+    pub code_stack: PushStack<Item>,
     pub exec_stack: PushStack<Item>,
     pub float_stack: PushStack<f32>,
     pub index_stack: PushStack<Index>,
@@ -56,6 +69,23 @@ pub struct PushState {
     pub configuration: PushConfiguration,
     pub quote_name: bool,
     pub send_name: bool,
+
+    // Progress of the current program, see PushInterpreter::run_for. Steps and
+    // elapsed time are kept after the program has ended until the next one starts.
+    pub started: bool,
+    pub steps: usize,
+    pub elapsed: Duration,
+
+    // Source of all random values (code, vectors, ERCs and names)
+    pub rng: ChaCha8Rng,
+
+    // Changes of the executed steps or None if they are not recorded,
+    // see PushInterpreter::step_back
+    #[cfg_attr(feature = "serde", serde(skip))]
+    pub journal: Option<Journal>,
+    // Previous values of the bindings changed by bind while a delta is recorded
+    #[cfg_attr(feature = "serde", serde(skip))]
+    pub(crate) binding_delta: Option<Vec<(String, Option<Item>)>>,
 }
 
 impl PushState {
@@ -78,6 +108,34 @@ impl PushState {
             configuration: PushConfiguration::new(),
             quote_name: false,
             send_name: false,
+            started: false,
+            steps: 0,
+            elapsed: Duration::from_millis(0),
+            rng: ChaCha8Rng::from_entropy(),
+            journal: None,
+            binding_delta: None,
+        }
+    }
+
+    /// Creates a new state whose random number generator is initialized with the
+    /// given seed. States with the same seed produce identical runs.
+    pub fn with_seed(seed: u64) -> Self {
+        let mut push_state = PushState::new();
+        push_state.rng = ChaCha8Rng::seed_from_u64(seed);
+        push_state
+    }
+
+    /// Binds the item to the name. Instructions use this method instead of
+    /// inserting into name_bindings so that the change is journaled.
+    pub fn bind(&mut self, name: String, item: Item) {
+        match &mut self.binding_delta {
+            Some(delta) => {
+                let previous = self.name_bindings.insert(name.clone(), item);
+                delta.push((name, previous));
+            }
+            None => {
+                self.name_bindings.insert(name, item);
+            }
         }
     }
 
@@ -93,6 +151,34 @@ impl PushState {
             + self.float_vector_stack.size()
             + self.int_vector_stack.size()
     }
+
+    /// Returns the total number of modifications of all stacks including IO and graph
+    /// stacks. Comparing it before and after an instruction tells if the instruction
+    /// had any effect on the stacks.
+    pub fn changes(&self) -> usize {
+        self.bool_stack.changes()
+            + self.float_stack.changes()
+            + self.index_stack.changes()
+            + self.int_stack.changes()
+            + self.name_stack.changes()
+            + self.code_stack.changes()
+            + self.exec_stack.changes()
+            + self.bool_vector_stack.changes()
+            + self.float_vector_stack.changes()
+            + self.int_vector_stack.changes()
+            + self.input_stack.changes()
+            + self.output_stack.changes()
+            + self.graph_stack.changes()
+    }
+
+    /// Returns the current change mark, see ChangeMark.
+    pub fn change_mark(&self) -> ChangeMark {
+        ChangeMark {
+            changes: self.changes(),
+            quote_name: self.quote_name,
+            send_name: self.send_name,
+        }
+    }
 }
 
 impl fmt::Display for PushState {
@@ -139,4 +225,34 @@ mod tests {
             .insert("Var1".to_string(), Item::bool(true));
         assert_eq!(test_state.to_string(), "> BOOL  : \n\n> CODE  : \n\n> EXEC  : \n\n> FLOAT : \n\n> GRAPH : \n\n> INDEX : \n\n> INT   : \n\n> BVEC  : \n\n> FVEC  : \n\n> IVEC  : \n\n> NAME  : \n\n> IDS   : \nVar1 => TRUE\n Var2 => INTVECTOR.BOOLINDEX\n \n")
     }
+
+    #[test]
+    #[cfg(feature = "serde")]
+    fn push_state_is_restored_from_json() {
+        use crate::push::io::PushMessage;
+        use crate::push::vector::IntVector;
+        use rand::Rng;
+        let mut test_state = PushState::with_seed(7);
+        test_state.int_stack.push(3);
+        test_state.float_vector_stack.push(FloatVector::new(vec![1.5, -2.0]));
+        test_state.exec_stack.push(Item::list(vec![
+            Item::int(1),
+            Item::instruction("INTEGER.+".to_string()),
+        ]));
+        test_state.input_stack.push(PushMessage::new(
+            IntVector::new(vec![1]),
+            BoolVector::from_int_array(vec![1, 0]),
+        ));
+        let mut graph = Graph::new();
+        graph.add_node(4);
+        test_state.graph_stack.push(graph);
+        test_state.name_bindings.insert("Var1".to_string(), Item::bool(true));
+        test_state.steps = 12;
+        let json = serde_json::to_string(&test_state).unwrap();
+        let mut restored_state: PushState = serde_json::from_str(&json).unwrap();
+        assert_eq!(restored_state.to_string(), test_state.to_string());
+        assert_eq!(restored_state.input_stack.to_string(), test_state.input_stack.to_string());
+        assert_eq!(restored_state.steps, 12);
+        assert_eq!(restored_state.rng.gen::<u64>(), test_state.rng.gen::<u64>());
+    }
 }
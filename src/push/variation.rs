@@ -0,0 +1,235 @@
+use crate::push::instructions::InstructionCache;
+use crate::push::item::{Item, PushType};
+use crate::push::random::CodeGenerator;
+use crate::push::state::PushState;
+use rand::Rng;
+
+/// Genetic operators for programs. Subtrees are addressed by their depth
+/// first position as in Item::traverse where position 0 is the program
+/// itself. All random values are drawn from the random number generator of
+/// the push state. Operators that can grow a program return a copy of the
+/// parent if the child would exceed max_points_in_program.
+pub struct Variation {}
+
+impl Variation {
+    /// Replaces a random subtree by random code with at most
+    /// max_subtree_points points.
+    pub fn subtree_mutation(
+        push_state: &mut PushState,
+        instructions: &InstructionCache,
+        program: &Item,
+        max_subtree_points: usize,
+    ) -> Item {
+        let sizes = Variation::subtree_sizes(program);
+        let point = push_state.rng.gen_range(0..sizes.len());
+        let max_points = usize::min(
+            max_subtree_points,
+            Variation::max_points(push_state).saturating_sub(sizes[0] - sizes[point]),
+        );
+        if max_points == 0 {
+            return program.clone();
+        }
+        let points = push_state.rng.gen_range(1..=max_points);
+        let code = CodeGenerator::random_code_with_size(push_state, instructions, points);
+        Item::replace_at(program, point, &code).unwrap()
+    }
+
+    /// Mutates each literal with the given probability. Booleans are negated,
+    /// integers and floats are replaced by random values within the bounds of
+    /// the configuration and vectors get a random element replaced. The
+    /// structure, instructions and names are not changed.
+    pub fn point_mutation(push_state: &mut PushState, program: &Item, rate: f32) -> Item {
+        match program {
+            Item::List { items } => {
+                let mut elements = items.copy_vec(items.size()).unwrap();
+                for element in elements.iter_mut() {
+                    *element = Variation::point_mutation(push_state, element, rate);
+                }
+                Item::list(elements)
+            }
+            Item::Literal { push_type } if push_state.rng.gen::<f32>() < rate => Item::Literal {
+                push_type: Variation::mutate_literal(push_state, push_type),
+            },
+            _ => program.clone(),
+        }
+    }
+
+    /// Replaces a random subtree of the first parent by a random subtree of
+    /// the second parent. Only subtrees of the second parent that keep the
+    /// child within the size limit are selected.
+    pub fn crossover(push_state: &mut PushState, first: &Item, second: &Item) -> Item {
+        let first_sizes = Variation::subtree_sizes(first);
+        let point = push_state.rng.gen_range(0..first_sizes.len());
+        let max_points =
+            Variation::max_points(push_state).saturating_sub(first_sizes[0] - first_sizes[point]);
+        let candidates: Vec<usize> = Variation::subtree_sizes(second)
+            .iter()
+            .enumerate()
+            .filter(|(_, size)| **size <= max_points)
+            .map(|(donor_point, _)| donor_point)
+            .collect();
+        if candidates.is_empty() {
+            return first.clone();
+        }
+        let donor_point = candidates[push_state.rng.gen_range(0..candidates.len())];
+        let subtree = Item::traverse(second, donor_point).unwrap();
+        Item::replace_at(first, point, &subtree).unwrap()
+    }
+
+    /// Returns a random subtree of the program as new program.
+    pub fn hoist_mutation(push_state: &mut PushState, program: &Item) -> Item {
+        let point = push_state.rng.gen_range(0..Item::size(program));
+        Item::traverse(program, point).unwrap()
+    }
+
+    /// Replaces a random list of the program by one of its own subtrees.
+    /// Returns a copy of the program if it does not contain a non-empty list.
+    pub fn shrink_mutation(push_state: &mut PushState, program: &Item) -> Item {
+        let sizes = Variation::subtree_sizes(program);
+        let lists: Vec<usize> = (0..sizes.len()).filter(|point| sizes[*point] > 1).collect();
+        if lists.is_empty() {
+            return program.clone();
+        }
+        let point = lists[push_state.rng.gen_range(0..lists.len())];
+        let list = Item::traverse(program, point).unwrap();
+        let inner_point = push_state.rng.gen_range(1..sizes[point]);
+        let subtree = Item::traverse(&list, inner_point).unwrap();
+        Item::replace_at(program, point, &subtree).unwrap()
+    }
+
+    /// Returns the size of the subtree at each depth first position.
+    pub fn subtree_sizes(program: &Item) -> Vec<usize> {
+        let mut sizes = vec![];
+        Variation::collect_sizes(program, &mut sizes);
+        sizes
+    }
+
+    fn collect_sizes(item: &Item, sizes: &mut Vec<usize>) -> usize {
+        let point = sizes.len();
+        sizes.push(1);
+        if let Item::List { items } = item {
+            for i in 0..items.size() {
+                sizes[point] += Variation::collect_sizes(items.get(i).unwrap(), sizes);
+            }
+        }
+        sizes[point]
+    }
+
+    fn max_points(push_state: &PushState) -> usize {
+        i32::max(push_state.configuration.max_points_in_program, 0) as usize
+    }
+
+    fn mutate_literal(push_state: &mut PushState, push_type: &PushType) -> PushType {
+        let mut mutated = push_type.clone();
+        match &mut mutated {
+            PushType::Bool { val } => *val = !*val,
+            PushType::Int { val } => {
+                *val = CodeGenerator::random_integer(push_state).unwrap_or(*val);
+            }
+            PushType::Float { val } => {
+                *val = CodeGenerator::random_float(push_state).unwrap_or(*val);
+            }
+            PushType::BoolVector { val } if !val.values.is_empty() => {
+                let i = push_state.rng.gen_range(0..val.values.len());
+                val.values[i] = !val.values[i];
+            }
+            PushType::IntVector { val } if !val.values.is_empty() => {
+                let i = push_state.rng.gen_range(0..val.values.len());
+                val.values[i] = CodeGenerator::random_integer(push_state).unwrap_or(val.values[i]);
+            }
+            PushType::FloatVector { val } if !val.values.is_empty() => {
+                let i = push_state.rng.gen_range(0..val.values.len());
+                val.values[i] = CodeGenerator::random_float(push_state).unwrap_or(val.values[i]);
+            }
+            _ => (),
+        }
+        mutated
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::push::instructions::InstructionSet;
+    use crate::push::parser::PushParser;
+
+    fn parse(instruction_set: &InstructionSet, code: &str) -> Item {
+        PushParser::parse(instruction_set, code).unwrap()
+    }
+
+    #[test]
+    fn subtree_sizes_are_in_depth_first_order() {
+        let mut instruction_set = InstructionSet::new();
+        instruction_set.load();
+        let program = parse(&instruction_set, "( 1 ( 2 3 ) ( ) 4 )");
+        assert_eq!(
+            Variation::subtree_sizes(&program),
+            vec![7, 1, 3, 1, 1, 1, 1]
+        );
+    }
+
+    #[test]
+    fn subtree_mutation_respects_max_points() {
+        let mut instruction_set = InstructionSet::new();
+        instruction_set.load();
+        let instructions = instruction_set.cache();
+        let mut push_state = PushState::with_seed(1);
+        push_state.configuration.max_points_in_program = 12;
+        let program = parse(&instruction_set, "( 1 ( 2 INTEGER.+ ) ( FLOAT.* TRUE ) 4 )");
+        for _ in 0..100 {
+            let child = Variation::subtree_mutation(&mut push_state, &instructions, &program, 10);
+            assert!(Item::size(&child) <= 12);
+        }
+    }
+
+    #[test]
+    fn point_mutation_keeps_structure() {
+        let mut instruction_set = InstructionSet::new();
+        instruction_set.load();
+        let mut push_state = PushState::with_seed(2);
+        let program = parse(&instruction_set, "( 1 ( TRUE INTEGER.+ ) 2.5 INT[1,2] name )");
+        let child = Variation::point_mutation(&mut push_state, &program, 1.0);
+        assert_eq!(Item::size(&child), Item::size(&program));
+        assert_eq!(Item::traverse(&child, 3).unwrap().to_string(), "FALSE");
+        assert_eq!(Item::traverse(&child, 4).unwrap().to_string(), "INTEGER.+");
+        assert_eq!(Item::traverse(&child, 7).unwrap().to_string(), "name");
+        let unchanged = Variation::point_mutation(&mut push_state, &program, 0.0);
+        assert!(Item::equals(&unchanged, &program));
+    }
+
+    #[test]
+    fn crossover_combines_subtrees_of_parents() {
+        let mut instruction_set = InstructionSet::new();
+        instruction_set.load();
+        let mut push_state = PushState::with_seed(3);
+        push_state.configuration.max_points_in_program = 8;
+        let first = parse(&instruction_set, "( 1 2 ( 3 4 ) )");
+        let second = parse(&instruction_set, "( TRUE ( FALSE ( FALSE ) ) INTEGER.- )");
+        for _ in 0..100 {
+            let child = Variation::crossover(&mut push_state, &first, &second);
+            assert!(Item::size(&child) <= 8);
+        }
+        push_state.configuration.max_points_in_program = 0;
+        let child = Variation::crossover(&mut push_state, &first, &second);
+        assert!(Item::equals(&child, &first));
+    }
+
+    #[test]
+    fn hoist_and_shrink_return_smaller_programs() {
+        let mut instruction_set = InstructionSet::new();
+        instruction_set.load();
+        let mut push_state = PushState::with_seed(4);
+        let program = parse(&instruction_set, "( 1 ( 2 ( 3 INTEGER.+ ) ) 4 )");
+        for _ in 0..50 {
+            let hoisted = Variation::hoist_mutation(&mut push_state, &program);
+            assert!(Item::contains(&program, &hoisted, 0).is_ok());
+            let shrunk = Variation::shrink_mutation(&mut push_state, &program);
+            assert!(Item::size(&shrunk) < Item::size(&program));
+        }
+        let atom = Item::int(1);
+        assert!(Item::equals(
+            &Variation::shrink_mutation(&mut push_state, &atom),
+            &atom
+        ));
+    }
+}
@@ -1,5 +1,6 @@
 use crate::push::instructions::Instruction;
 use crate::push::instructions::InstructionCache;
+use crate::push::instructions::InstructionId;
 use crate::push::item::Item;
 use crate::push::random::CodeGenerator;
 use crate::push::state::PushState;
@@ -7,8 +8,10 @@ use crate::push::state::*;
 use crate::push::stack::PushPrint;
 use std::collections::HashMap;
 use std::fmt;
+use std::sync::OnceLock;
 
 #[derive(Clone, Debug, Default)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub struct BoolVector {
     pub values: Vec<bool>,
 }
@@ -54,6 +57,7 @@ impl PartialEq for BoolVector {
 }
 
 #[derive(Clone, Debug, Default)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub struct IntVector {
     pub values: Vec<i32>,
 }
@@ -89,6 +93,7 @@ impl PartialEq for IntVector {
 }
 
 #[derive(Clone, Debug, Default)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub struct FloatVector {
     pub values: Vec<f32>,
 }
@@ -123,6 +128,9 @@ impl PartialEq for FloatVector {
     }
 }
 
+// Id of the instruction that is pushed by INTVECTOR.LOOP
+static INT_VECTOR_LOOP_ID: OnceLock<InstructionId> = OnceLock::new();
+
 pub fn load_vector_instructions(map: &mut HashMap<String, Instruction>) {
     map.insert(
         String::from("BOOLVECTOR.GET"),
@@ -297,10 +305,7 @@ pub fn load_vector_instructions(map: &mut HashMap<String, Instruction>) {
         String::from("INTVECTOR.LENGTH"),
         Instruction::new(int_vector_length),
     );
-    map.insert(
-        String::from("INTVECTOR.LOOP"),
-        Instruction::new(int_vector_loop),
-    );
+    map.insert(String::from("INTVECTOR.LOOP"), Instruction::with_blocks(int_vector_loop, 1));
     map.insert(
         String::from("INTVECTOR.POP"),
         Instruction::new(int_vector_pop),
@@ -582,7 +587,7 @@ pub fn bool_vector_not(push_state: &mut PushState, _instruction_cache: &Instruct
 pub fn bool_vector_define(push_state: &mut PushState, _instruction_cache: &InstructionCache) {
     if let Some(name) = push_state.name_stack.pop() {
         if let Some(bvval) = push_state.bool_vector_stack.pop() {
-            push_state.name_bindings.insert(name, Item::boolvec(bvval));
+            push_state.bind(name, Item::boolvec(bvval));
         }
     }
 }
@@ -638,7 +643,7 @@ pub fn bool_vector_pop(push_state: &mut PushState, _instruction_cache: &Instruct
 pub fn bool_vector_rand(push_state: &mut PushState, _instruction_cache: &InstructionCache) {
     if let Some(size) = push_state.int_stack.pop() {
         if let Some(sparsity) = push_state.float_stack.pop() {
-            if let Some(rbvval) = CodeGenerator::random_bool_vector(size, sparsity) {
+            if let Some(rbvval) = CodeGenerator::random_bool_vector(&mut push_state.rng, size, sparsity) {
                 push_state.bool_vector_stack.push(rbvval);
             }
         }
@@ -922,7 +927,7 @@ pub fn int_vector_contains(push_state: &mut PushState, _instruction_cache: &Inst
 pub fn int_vector_define(push_state: &mut PushState, _instruction_cache: &InstructionCache) {
     if let Some(name) = push_state.name_stack.pop() {
         if let Some(ivval) = push_state.int_vector_stack.pop() {
-            push_state.name_bindings.insert(name, Item::intvec(ivval));
+            push_state.bind(name, Item::intvec(ivval));
         }
     }
 }
@@ -983,7 +988,9 @@ pub fn int_vector_loop(push_state: &mut PushState, _instruction_cache: &Instruct
                 let next_element = array.values.remove(0);
                 let updated_loop = Item::list(vec![
                                               body.clone(),
-                                              Item::instruction("INTVECTOR.LOOP".to_string()),
+                                              Item::InstructionMeta {
+                                                  id: InstructionId::cached(&INT_VECTOR_LOOP_ID, "INTVECTOR.LOOP"),
+                                              },
                                               Item::intvec(array),
                 ]);
                 push_state.exec_stack.push(updated_loop);
@@ -1027,7 +1034,7 @@ pub fn int_vector_rand(push_state: &mut PushState, _instruction_cache: &Instruct
         // 1 params[2] -> size
         // 2 params[1] -> max
         // 3 params[0] -> min
-        if let Some(rbvval) = CodeGenerator::random_int_vector(params[2], params[0], params[1]) {
+        if let Some(rbvval) = CodeGenerator::random_int_vector(&mut push_state.rng, params[2], params[0], params[1]) {
             push_state.int_vector_stack.push(rbvval);
         }
     }
@@ -1302,7 +1309,7 @@ pub fn float_vector_divide(push_state: &mut PushState, _instruction_cache: &Inst
 pub fn float_vector_define(push_state: &mut PushState, _instruction_cache: &InstructionCache) {
     if let Some(name) = push_state.name_stack.pop() {
         if let Some(fvval) = push_state.float_vector_stack.pop() {
-            push_state.name_bindings.insert(name, Item::floatvec(fvval));
+            push_state.bind(name, Item::floatvec(fvval));
         }
     }
 }
@@ -1388,7 +1395,7 @@ pub fn float_vector_rand(push_state: &mut PushState, _instruction_cache: &Instru
             // 1 gauss_params[1]: mean
             // 2 gauss_params[0]: stddev
             if let Some(rfvval) =
-                CodeGenerator::random_float_vector(size, gauss_params[1], gauss_params[0])
+                CodeGenerator::random_float_vector(&mut push_state.rng, size, gauss_params[1], gauss_params[0])
             {
                 push_state.float_vector_stack.push(rfvval);
             }